@@ -0,0 +1,423 @@
+use std::fmt::{Display, Formatter};
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Sub, SubAssign};
+
+use crate::parser::PieceColor;
+use crate::square::{File, Rank, Square};
+
+/// A set of squares, stored as one bit per square where bit 0 is A1 and bit 63 is H8,
+/// the same order as [`Square::index`]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+	pub const EMPTY: Bitboard = Bitboard(0);
+	pub const FULL: Bitboard = Bitboard(u64::MAX);
+	/// The squares of the same color as A1
+	pub const DARK_SQUARES: Bitboard = Bitboard(0xaa55_aa55_aa55_aa55);
+	/// The squares of the same color as H1
+	pub const LIGHT_SQUARES: Bitboard = Bitboard(!0xaa55_aa55_aa55_aa55);
+	
+	/// The set of only the given square
+	pub fn from_square(square: Square) -> Self {
+		Bitboard(1 << square.index())
+	}
+	
+	/// The set of every square on the given file
+	pub fn file(file: File) -> Self {
+		Bitboard(0x0101_0101_0101_0101 << file.index())
+	}
+	
+	/// The set of every square on the given rank
+	pub fn rank(rank: Rank) -> Self {
+		Bitboard(0xff << (rank.index() * 8))
+	}
+	
+	pub fn contains(self, square: Square) -> bool {
+		self.0 & (1 << square.index()) != 0
+	}
+	
+	pub fn insert(&mut self, square: Square) {
+		self.0 |= 1 << square.index();
+	}
+	
+	pub fn remove(&mut self, square: Square) {
+		self.0 &= !(1 << square.index());
+	}
+	
+	pub fn is_empty(self) -> bool {
+		self.0 == 0
+	}
+	
+	/// The number of squares in the set
+	pub fn count(self) -> u32 {
+		self.0.count_ones()
+	}
+	
+	/// Whether the set has at least two squares
+	pub fn more_than_one(self) -> bool {
+		self.0 & self.0.wrapping_sub(1) != 0
+	}
+	
+	/// The square with the lowest index in the set, if any
+	pub fn first(self) -> Option<Square> {
+		Square::from_index(self.0.trailing_zeros() as usize)
+	}
+	
+	/// The square with the highest index in the set, if any
+	pub fn last(self) -> Option<Square> {
+		63usize.checked_sub(self.0.leading_zeros() as usize).and_then(Square::from_index)
+	}
+	
+	/// Every square in the set, from the lowest index to the highest
+	pub fn squares(self) -> Squares {
+		Squares(self)
+	}
+}
+
+/// An iterator over the squares of a [`Bitboard`], see [`Bitboard::squares`]
+#[derive(Debug, Clone)]
+pub struct Squares(Bitboard);
+
+impl Iterator for Squares {
+	type Item = Square;
+	
+	fn next(&mut self) -> Option<Self::Item> {
+		let square = self.0.first()?;
+		self.0 .0 &= self.0 .0 - 1;
+		Some(square)
+	}
+	
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let count = self.0.count() as usize;
+		(count, Some(count))
+	}
+}
+
+impl ExactSizeIterator for Squares {}
+
+impl IntoIterator for Bitboard {
+	type Item = Square;
+	type IntoIter = Squares;
+	
+	fn into_iter(self) -> Self::IntoIter {
+		self.squares()
+	}
+}
+
+impl FromIterator<Square> for Bitboard {
+	fn from_iter<T: IntoIterator<Item = Square>>(iter: T) -> Self {
+		let mut bitboard = Bitboard::EMPTY;
+		for square in iter {
+			bitboard.insert(square);
+		}
+		bitboard
+	}
+}
+
+impl From<Square> for Bitboard {
+	fn from(square: Square) -> Self {
+		Bitboard::from_square(square)
+	}
+}
+
+impl BitAnd for Bitboard {
+	type Output = Bitboard;
+	
+	fn bitand(self, rhs: Self) -> Self::Output {
+		Bitboard(self.0 & rhs.0)
+	}
+}
+
+impl BitOr for Bitboard {
+	type Output = Bitboard;
+	
+	fn bitor(self, rhs: Self) -> Self::Output {
+		Bitboard(self.0 | rhs.0)
+	}
+}
+
+impl BitXor for Bitboard {
+	type Output = Bitboard;
+	
+	fn bitxor(self, rhs: Self) -> Self::Output {
+		Bitboard(self.0 ^ rhs.0)
+	}
+}
+
+/// The squares in the left set that are not in the right set
+impl Sub for Bitboard {
+	type Output = Bitboard;
+	
+	fn sub(self, rhs: Self) -> Self::Output {
+		Bitboard(self.0 & !rhs.0)
+	}
+}
+
+impl Not for Bitboard {
+	type Output = Bitboard;
+	
+	fn not(self) -> Self::Output {
+		Bitboard(!self.0)
+	}
+}
+
+impl BitAndAssign for Bitboard {
+	fn bitand_assign(&mut self, rhs: Self) {
+		self.0 &= rhs.0;
+	}
+}
+
+impl BitOrAssign for Bitboard {
+	fn bitor_assign(&mut self, rhs: Self) {
+		self.0 |= rhs.0;
+	}
+}
+
+impl BitXorAssign for Bitboard {
+	fn bitxor_assign(&mut self, rhs: Self) {
+		self.0 ^= rhs.0;
+	}
+}
+
+impl SubAssign for Bitboard {
+	fn sub_assign(&mut self, rhs: Self) {
+		self.0 &= !rhs.0;
+	}
+}
+
+/// Draws the set as a board seen from White's side, with `X` for squares in the set
+impl Display for Bitboard {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		for rank in Rank::ALL.iter().rev() {
+			let mut output_string = String::new();
+			
+			for file in File::ALL {
+				output_string.push(if self.contains(Square::new(file, *rank)) { 'X' } else { '.' });
+			}
+			
+			writeln!(f, "{output_string}")?;
+		}
+		
+		Ok(())
+	}
+}
+
+/// The file and rank steps of the eight sliding directions,
+/// the first four going up the square indices and the last four going down
+const DIRECTIONS: [(i32, i32); 8] = [(0, 1), (1, 1), (1, 0), (-1, 1), (0, -1), (-1, -1), (-1, 0), (1, -1)];
+
+const KNIGHT_STEPS: [(i32, i32); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+
+const KING_STEPS: [(i32, i32); 8] = [(0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (-1, 1)];
+
+const fn step_table(steps: &[(i32, i32); 8]) -> [u64; 64] {
+	let mut table = [0; 64];
+	let mut square = 0;
+	
+	while square < 64 {
+		let file = (square % 8) as i32;
+		let rank = (square / 8) as i32;
+		let mut i = 0;
+		
+		while i < 8 {
+			let target_file = file + steps[i].0;
+			let target_rank = rank + steps[i].1;
+			
+			if target_file >= 0 && target_file < 8 && target_rank >= 0 && target_rank < 8 {
+				table[square] |= 1 << (target_rank * 8 + target_file);
+			}
+			i += 1;
+		}
+		square += 1;
+	}
+	
+	table
+}
+
+const fn ray_table() -> [[u64; 64]; 8] {
+	let mut table = [[0; 64]; 8];
+	let mut direction = 0;
+	
+	while direction < 8 {
+		let mut square = 0;
+		
+		while square < 64 {
+			let mut file = (square % 8) as i32 + DIRECTIONS[direction].0;
+			let mut rank = (square / 8) as i32 + DIRECTIONS[direction].1;
+			
+			while file >= 0 && file < 8 && rank >= 0 && rank < 8 {
+				table[direction][square] |= 1 << (rank * 8 + file);
+				file += DIRECTIONS[direction].0;
+				rank += DIRECTIONS[direction].1;
+			}
+			square += 1;
+		}
+		direction += 1;
+	}
+	
+	table
+}
+
+const fn pawn_table(step: i32) -> [u64; 64] {
+	let mut table = [0; 64];
+	let mut square = 0;
+	
+	while square < 64 {
+		let file = (square % 8) as i32;
+		let rank = (square / 8) as i32 + step;
+		
+		if rank >= 0 && rank < 8 {
+			if file > 0 {
+				table[square] |= 1 << (rank * 8 + file - 1);
+			}
+			if file < 7 {
+				table[square] |= 1 << (rank * 8 + file + 1);
+			}
+		}
+		square += 1;
+	}
+	
+	table
+}
+
+static KNIGHT_ATTACKS: [u64; 64] = step_table(&KNIGHT_STEPS);
+static KING_ATTACKS: [u64; 64] = step_table(&KING_STEPS);
+static WHITE_PAWN_ATTACKS: [u64; 64] = pawn_table(1);
+static BLACK_PAWN_ATTACKS: [u64; 64] = pawn_table(-1);
+static RAYS: [[u64; 64]; 8] = ray_table();
+
+pub fn knight_attacks(square: Square) -> Bitboard {
+	Bitboard(KNIGHT_ATTACKS[square.index()])
+}
+
+pub fn king_attacks(square: Square) -> Bitboard {
+	Bitboard(KING_ATTACKS[square.index()])
+}
+
+/// The squares a pawn of the given color attacks diagonally, empty for [`PieceColor::Empty`]
+pub fn pawn_attacks(color: PieceColor, square: Square) -> Bitboard {
+	match color {
+		PieceColor::White => Bitboard(WHITE_PAWN_ATTACKS[square.index()]),
+		PieceColor::Black => Bitboard(BLACK_PAWN_ATTACKS[square.index()]),
+		PieceColor::Empty => Bitboard::EMPTY
+	}
+}
+
+/// The squares a bishop attacks, up to and including the first occupied square in every direction
+pub fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+	slider_attacks(square, occupied, &[1, 3, 5, 7])
+}
+
+/// The squares a rook attacks, up to and including the first occupied square in every direction
+pub fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+	slider_attacks(square, occupied, &[0, 2, 4, 6])
+}
+
+pub fn queen_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+	bishop_attacks(square, occupied) | rook_attacks(square, occupied)
+}
+
+/// The squares strictly between two squares on a shared rank, file or diagonal,
+/// empty if the squares do not share one
+pub fn between(from: Square, to: Square) -> Bitboard {
+	for rays in &RAYS {
+		let ray = rays[from.index()];
+		
+		if ray & (1 << to.index()) != 0 {
+			let beyond = rays[to.index()];
+			return Bitboard(ray & !beyond & !(1 << to.index()));
+		}
+	}
+	
+	Bitboard::EMPTY
+}
+
+fn slider_attacks(square: Square, occupied: Bitboard, directions: &[usize; 4]) -> Bitboard {
+	let mut attacks = 0;
+	
+	for &direction in directions {
+		let ray = RAYS[direction][square.index()];
+		let blockers = Bitboard(ray & occupied.0);
+		
+		let blocker = if direction < 4 { blockers.first() } else { blockers.last() };
+		
+		attacks |= match blocker {
+			Some(blocker) => ray & !RAYS[direction][blocker.index()],
+			None => ray
+		};
+	}
+	
+	Bitboard(attacks)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn squares(names: &[&str]) -> Bitboard {
+		names.iter().map(|name| Square::from_algebraic(name).unwrap()).collect()
+	}
+	
+	fn square(name: &str) -> Square {
+		Square::from_algebraic(name).unwrap()
+	}
+	
+	#[test]
+	fn step_attacks() {
+		assert_eq!(knight_attacks(square("a1")), squares(&["b3", "c2"]));
+		assert_eq!(knight_attacks(square("d4")).count(), 8);
+		assert_eq!(king_attacks(square("h8")), squares(&["g8", "g7", "h7"]));
+		assert_eq!(king_attacks(square("e1")), squares(&["d1", "f1", "d2", "e2", "f2"]));
+		assert_eq!(pawn_attacks(PieceColor::White, square("a2")), squares(&["b3"]));
+		assert_eq!(pawn_attacks(PieceColor::Black, square("e7")), squares(&["d6", "f6"]));
+		assert_eq!(pawn_attacks(PieceColor::White, square("e8")), Bitboard::EMPTY);
+	}
+	
+	#[test]
+	fn sliding_attacks() {
+		let blockers = squares(&["d6", "b4", "d2", "g7"]);
+		
+		assert_eq!(
+			rook_attacks(square("d4"), blockers),
+			squares(&["d5", "d6", "c4", "b4", "e4", "f4", "g4", "h4", "d3", "d2"])
+		);
+		assert_eq!(rook_attacks(square("a1"), Bitboard::EMPTY).count(), 14);
+		assert_eq!(bishop_attacks(square("d4"), blockers) & squares(&["e5", "f6", "g7", "h8"]), squares(&["e5", "f6", "g7"]));
+		assert_eq!(bishop_attacks(square("a1"), Bitboard::EMPTY).count(), 7);
+		assert_eq!(queen_attacks(square("d4"), Bitboard::EMPTY).count(), 27);
+	}
+	
+	#[test]
+	fn between_squares() {
+		assert_eq!(between(square("a1"), square("d4")), squares(&["b2", "c3"]));
+		assert_eq!(between(square("e8"), square("e1")), squares(&["e7", "e6", "e5", "e4", "e3", "e2"]));
+		assert_eq!(between(square("a1"), square("b2")), Bitboard::EMPTY);
+		assert_eq!(between(square("a1"), square("b3")), Bitboard::EMPTY);
+	}
+	
+	#[test]
+	fn set_operations() {
+		let a = squares(&["a1", "e4", "h8"]);
+		let b = squares(&["e4", "d5"]);
+		
+		assert_eq!(a | b, squares(&["a1", "e4", "h8", "d5"]));
+		assert_eq!(a & b, squares(&["e4"]));
+		assert_eq!(a ^ b, squares(&["a1", "h8", "d5"]));
+		assert_eq!(a - b, squares(&["a1", "h8"]));
+		assert_eq!(!Bitboard::EMPTY, Bitboard::FULL);
+		assert_eq!(a.count(), 3);
+		assert!(a.more_than_one() && !squares(&["e4"]).more_than_one());
+		assert_eq!(a.first(), Some(square("a1")));
+		assert_eq!(a.last(), Some(square("h8")));
+		assert_eq!(a.into_iter().collect::<Vec<_>>(), [square("a1"), square("e4"), square("h8")]);
+		assert_eq!(Bitboard::file(File::C).count(), 8);
+		assert!(Bitboard::rank(Rank::Eight).contains(square("h8")));
+		
+		let mut set = Bitboard::EMPTY;
+		set.insert(square("c3"));
+		set.insert(square("c3"));
+		assert_eq!(set, Bitboard::from_square(square("c3")));
+		set.remove(square("c3"));
+		assert!(set.is_empty());
+	}
+}
@@ -0,0 +1,162 @@
+use crate::moves::CastlingSide;
+use crate::parser::{CastlingRights, Fen, Piece, PieceColor, PieceType, Row};
+use crate::random::Random;
+use crate::square::{File, Rank};
+
+/// The number of starting positions in Chess960
+pub const POSITION_COUNT: usize = 960;
+
+/// The index of the standard chess starting position, `RNBQKBNR`
+pub const STANDARD_INDEX: usize = 518;
+
+/// Where the two knights go among the five squares left after placing the bishops and the queen,
+/// by the last digit of the Scharnagl numbering
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+impl Row {
+	/// The back rank of the Chess960 starting position with the given Scharnagl index from 0 to 959,
+	/// with every piece in the given color, `None` if the index is 960 or more.
+	/// 
+	/// The index picks the file of the light-squared bishop, then the dark-squared bishop, the queen and the knights,
+	/// and the rooks and king fill the three squares left with the king in the middle. Index 518 is the standard setup
+	pub fn chess960(index: usize, color: PieceColor) -> Option<Row> {
+		if index >= POSITION_COUNT {
+			return None;
+		}
+		
+		let mut types = [PieceType::Empty; 8];
+		let mut rest = index;
+		
+		types[2 * (rest % 4) + 1] = PieceType::Bishop;
+		rest /= 4;
+		types[2 * (rest % 4)] = PieceType::Bishop;
+		rest /= 4;
+		
+		let empty = |types: &[PieceType; 8]| -> Vec<usize> {
+			(0..8).filter(|&file| types[file] == PieceType::Empty).collect()
+		};
+		
+		types[empty(&types)[rest % 6]] = PieceType::Queen;
+		rest /= 6;
+		
+		let squares = empty(&types);
+		let (first, second) = KNIGHT_PLACEMENTS[rest];
+		types[squares[first]] = PieceType::Knight;
+		types[squares[second]] = PieceType::Knight;
+		
+		for (file, piece_type) in empty(&types).into_iter().zip([PieceType::Rook, PieceType::King, PieceType::Rook]) {
+			types[file] = piece_type;
+		}
+		
+		Some(Row {
+			pieces: types.map(|piece_type| Piece::new(piece_type, color))
+		})
+	}
+	
+	/// The Scharnagl index of the row as a Chess960 back rank, the reverse of [`Row::chess960`],
+	/// or `None` if the row is not a Chess960 starting setup of a single color
+	pub fn chess960_index(&self) -> Option<usize> {
+		let color = self.pieces[0].color;
+		if color == PieceColor::Empty || self.pieces.iter().any(|piece| piece.color != color) {
+			return None;
+		}
+		
+		let types = self.pieces.map(|piece| piece.piece_type);
+		let files_of = |piece_type: PieceType| -> Vec<usize> {
+			(0..8).filter(|&file| types[file] == piece_type).collect()
+		};
+		
+		let bishops = files_of(PieceType::Bishop);
+		let light = bishops.iter().copied().find(|file| file % 2 == 1)?;
+		let dark = bishops.iter().copied().find(|file| file % 2 == 0)?;
+		
+		// The queen's place among the squares the bishops leave empty
+		let queen = files_of(PieceType::Queen).first().copied()?;
+		let queen = (0..queen).filter(|&file| file != light && file != dark).count();
+		
+		// The knights' places among the squares the bishops and queen leave empty
+		let others: Vec<usize> = (0..8).filter(|&file| !matches!(types[file], PieceType::Bishop | PieceType::Queen)).collect();
+		let knights: Vec<usize> = others
+			.iter()
+			.enumerate()
+			.filter(|&(_, &file)| types[file] == PieceType::Knight)
+			.map(|(place, _)| place)
+			.collect();
+		let knights = KNIGHT_PLACEMENTS.iter().position(|&(first, second)| knights == [first, second])?;
+		
+		let index = light / 2 + 4 * (dark / 2) + 16 * queen + 96 * knights;
+		
+		(Row::chess960(index, color).as_ref() == Some(self)).then_some(index)
+	}
+}
+
+impl Fen {
+	/// The Chess960 starting position with the given Scharnagl index from 0 to 959, see [`Row::chess960`],
+	/// with both sides able to castle with both rooks
+	pub fn chess960(index: usize) -> Option<Fen> {
+		Fen::double_chess960(index, index)
+	}
+	
+	/// A Double Fischer Random starting position, where White and Black each get their own back rank
+	/// by Scharnagl index, with both sides able to castle with both rooks
+	pub fn double_chess960(white_index: usize, black_index: usize) -> Option<Fen> {
+		let mut fen = Fen::default();
+		fen.rows[Rank::One.index()] = Row::chess960(white_index, PieceColor::White)?;
+		fen.rows[Rank::Eight.index()] = Row::chess960(black_index, PieceColor::Black)?;
+		fen.castling = CastlingRights::none();
+		
+		for (color, rank) in [(PieceColor::White, Rank::One), (PieceColor::Black, Rank::Eight)] {
+			let rooks: Vec<File> = File::ALL
+				.into_iter()
+				.filter(|&file| fen.row(rank).pieces[file.index()].piece_type == PieceType::Rook)
+				.collect();
+			
+			fen.castling.set(color, CastlingSide::QueenSide, rooks.first().copied());
+			fen.castling.set(color, CastlingSide::KingSide, rooks.last().copied());
+		}
+		
+		Some(fen)
+	}
+	
+	/// A Chess960 starting position picked at random, the same one for the same state of the generator
+	pub fn random_chess960(random: &mut Random) -> Fen {
+		let index = random.below(POSITION_COUNT as u64) as usize;
+		Fen::chess960(index).unwrap_or_default()
+	}
+	
+	/// A Double Fischer Random starting position picked at random, with a back rank for each side
+	pub fn random_double_chess960(random: &mut Random) -> Fen {
+		let white_index = random.below(POSITION_COUNT as u64) as usize;
+		let black_index = random.below(POSITION_COUNT as u64) as usize;
+		Fen::double_chess960(white_index, black_index).unwrap_or_default()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn standard_index() {
+		assert_eq!(Fen::chess960(STANDARD_INDEX), Some(Fen::default()));
+		assert_eq!(Fen::default().row(Rank::One).chess960_index(), Some(STANDARD_INDEX));
+		assert_eq!(Fen::default().row(Rank::Eight).chess960_index(), Some(STANDARD_INDEX));
+	}
+	
+	#[test]
+	fn index_round_trip() {
+		let mut rows = Vec::new();
+		
+		for index in 0..POSITION_COUNT {
+			let row = Row::chess960(index, PieceColor::White).unwrap();
+			
+			assert_eq!(row.chess960_index(), Some(index));
+			assert!(!rows.contains(&row));
+			rows.push(row);
+		}
+		
+		assert_eq!(Row::chess960(POSITION_COUNT, PieceColor::White), None);
+		assert_eq!(Row::chess960(0, PieceColor::White).unwrap().to_string(), "BBQNNRKR");
+		assert_eq!(Row::chess960(959, PieceColor::White).unwrap().to_string(), "RKRNNQBB");
+	}
+}
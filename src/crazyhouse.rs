@@ -0,0 +1,225 @@
+use std::fmt::{Display, Formatter};
+
+use crate::bitboard::Bitboard;
+use crate::moves::Move;
+use crate::parser::{Fen, FenError, ParseOptions, Piece, PieceColor, PieceType};
+use crate::position::{self, Position};
+use crate::square::Rank;
+
+/// The piece types that can be held in hand and dropped, in the order they are written
+pub const DROP_TYPES: [PieceType; 5] = [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight, PieceType::Pawn];
+
+/// The pieces each side has captured in crazyhouse or bughouse and can drop back onto the board as their own
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Holdings {
+	/// How many pieces of every type in [`DROP_TYPES`] White holds at index 0 and Black at index 1
+	counts: [[u8; 5]; 2]
+}
+
+/// How the holdings are written in a crazyhouse FEN
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum HoldingsSyntax {
+	/// In brackets right after the piece placement, like `RNBQKBNR[QRp]`
+	#[default]
+	Brackets,
+	/// As a ninth `/` segment of the piece placement, like `RNBQKBNR/QRp`
+	Slash
+}
+
+impl Holdings {
+	/// Holdings with no pieces in either hand
+	pub fn new() -> Self {
+		Holdings::default()
+	}
+	
+	/// How many pieces of the given type and color are held
+	pub fn count(&self, color: PieceColor, piece_type: PieceType) -> u8 {
+		match (position::color_index(color), drop_index(piece_type)) {
+			(Some(color), Some(piece_type)) => self.counts[color][piece_type],
+			_ => 0
+		}
+	}
+	
+	/// Puts a piece of the given type in the given color's hand, ignoring kings
+	pub fn add(&mut self, color: PieceColor, piece_type: PieceType) {
+		if let (Some(color), Some(piece_type)) = (position::color_index(color), drop_index(piece_type)) {
+			self.counts[color][piece_type] = self.counts[color][piece_type].saturating_add(1);
+		}
+	}
+	
+	/// Takes a piece of the given type out of the given color's hand, returning whether there was one
+	pub fn remove(&mut self, color: PieceColor, piece_type: PieceType) -> bool {
+		match (position::color_index(color), drop_index(piece_type)) {
+			(Some(color), Some(piece_type)) if self.counts[color][piece_type] > 0 => {
+				self.counts[color][piece_type] -= 1;
+				true
+			}
+			_ => false
+		}
+	}
+	
+	/// Whether neither side holds any piece
+	pub fn is_empty(&self) -> bool {
+		self.counts.iter().flatten().all(|&count| count == 0)
+	}
+	
+	/// Parses holdings like `QRp`, where `-` or nothing means empty hands
+	/// and `offset` is the byte offset of the holdings in the whole input
+	pub(crate) fn parse(field: &str, offset: usize, options: ParseOptions) -> Result<Self, FenError> {
+		let mut holdings = Holdings::new();
+		
+		if field == "-" {
+			return Ok(holdings);
+		}
+		
+		for (i, character) in field.char_indices() {
+			let piece = Piece::from_char(character)
+				.filter(|piece| drop_index(piece.piece_type).is_some())
+				.ok_or(FenError::InvalidHoldings {
+					offset: offset + i,
+					character
+				})?;
+			let color = if options.legacy_piece_case { piece.color.opposite() } else { piece.color };
+			
+			holdings.add(color, piece.piece_type);
+		}
+		
+		Ok(holdings)
+	}
+}
+
+/// The index of a piece type in [`DROP_TYPES`], `None` for kings and empty squares
+fn drop_index(piece_type: PieceType) -> Option<usize> {
+	DROP_TYPES.iter().position(|&other| other == piece_type)
+}
+
+impl Position {
+	/// Every drop of a held piece by the side to move onto an empty square,
+	/// leaving out pawns on the first and last rank
+	pub(crate) fn drop_moves(&self) -> Vec<Move> {
+		let Some(holdings) = self.holdings else {
+			return Vec::new();
+		};
+		
+		let us = self.side_to_move;
+		let empty = !self.occupied();
+		let back_ranks = Bitboard::rank(Rank::One) | Bitboard::rank(Rank::Eight);
+		let mut moves = Vec::new();
+		
+		for piece_type in DROP_TYPES.into_iter().filter(|&piece_type| holdings.count(us, piece_type) > 0) {
+			let targets = if piece_type == PieceType::Pawn { empty - back_ranks } else { empty };
+			
+			for to in targets {
+				moves.push(Move::new_drop(piece_type, to));
+			}
+		}
+		
+		moves
+	}
+}
+
+impl Fen {
+	/// Writes the position with its holdings in the given syntax, see [`HoldingsSyntax`].
+	/// 
+	/// The [`Display`] implementation writes holdings in brackets
+	pub fn to_crazyhouse_string(&self, syntax: HoldingsSyntax) -> String {
+		let fen = self.to_string();
+		
+		match (self.holdings, syntax) {
+			(Some(holdings), HoldingsSyntax::Slash) => {
+				let end = fen.find('[').unwrap_or(fen.len());
+				let rest = fen.find(']').map_or("", |close| &fen[close + 1..]);
+				format!("{}/{holdings}{rest}", &fen[..end])
+			}
+			_ => fen
+		}
+	}
+}
+
+/// Writes the held pieces like `QRp`, White's first, from queens down to pawns
+impl Display for Holdings {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		for color in [PieceColor::White, PieceColor::Black] {
+			for piece_type in DROP_TYPES {
+				for _ in 0..self.count(color, piece_type) {
+					write!(f, "{}", Piece::new(piece_type, color))?;
+				}
+			}
+		}
+		
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn parse_and_display() {
+		let holdings = Holdings::parse("pQnRqP", 0, ParseOptions::default()).unwrap();
+		
+		assert_eq!(holdings.count(PieceColor::White, PieceType::Pawn), 1);
+		assert_eq!(holdings.count(PieceColor::Black, PieceType::Pawn), 1);
+		assert_eq!(holdings.count(PieceColor::Black, PieceType::Knight), 1);
+		assert_eq!(holdings.count(PieceColor::White, PieceType::Bishop), 0);
+		assert_eq!(holdings.to_string(), "QRPqnp");
+		
+		let legacy = ParseOptions {
+			legacy_piece_case: true,
+			..ParseOptions::default()
+		};
+		assert_eq!(Holdings::parse("QRp", 0, legacy).unwrap().to_string(), "Pqr");
+		
+		assert_eq!(Holdings::parse("-", 0, ParseOptions::default()), Ok(Holdings::new()));
+		assert_eq!(Holdings::parse("", 0, ParseOptions::default()), Ok(Holdings::new()));
+		assert_eq!(
+			Holdings::parse("QK", 10, ParseOptions::default()),
+			Err(FenError::InvalidHoldings { offset: 11, character: 'K' })
+		);
+	}
+	
+	#[test]
+	fn add_and_remove() {
+		let mut holdings = Holdings::new();
+		assert!(holdings.is_empty());
+		
+		holdings.add(PieceColor::Black, PieceType::Knight);
+		holdings.add(PieceColor::Black, PieceType::Knight);
+		holdings.add(PieceColor::White, PieceType::King);
+		assert_eq!(holdings.to_string(), "nn");
+		
+		assert!(holdings.remove(PieceColor::Black, PieceType::Knight));
+		assert!(!holdings.remove(PieceColor::White, PieceType::Knight));
+		assert!(holdings.remove(PieceColor::Black, PieceType::Knight));
+		assert!(holdings.is_empty());
+	}
+	
+	#[test]
+	fn holdings_syntax() {
+		let brackets = Fen::parse("r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKB1R[Nn] w KQkq - 0 3").unwrap();
+		let slash = Fen::parse("r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKB1R/Nn w KQkq - 0 3").unwrap();
+		
+		assert_eq!(brackets, slash);
+		assert_eq!(brackets.to_string(), "r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKB1R[Nn] w KQkq - 0 3");
+		assert_eq!(brackets.to_crazyhouse_string(HoldingsSyntax::Brackets), brackets.to_string());
+		assert_eq!(brackets.to_crazyhouse_string(HoldingsSyntax::Slash), "r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKB1R/Nn w KQkq - 0 3");
+		
+		let empty = Fen::parse("4k3/8/8/8/8/8/8/4K3[] w - - 0 1").unwrap();
+		assert_eq!(empty.to_crazyhouse_string(HoldingsSyntax::Slash), "4k3/8/8/8/8/8/8/4K3/ w - - 0 1");
+		
+		let standard = Fen::default();
+		assert_eq!(standard.to_crazyhouse_string(HoldingsSyntax::Slash), standard.to_string());
+	}
+	
+	#[test]
+	fn drops() {
+		let position = Position::from(Fen::parse("4k3/8/8/8/8/8/8/4K3[Pn] w - - 0 1").unwrap());
+		let drops = position.drop_moves();
+		
+		// A pawn can go anywhere but the back ranks, and the black knight is not White's to drop
+		assert_eq!(drops.len(), 48);
+		assert!(drops.iter().all(|mv| (1..7).contains(&(mv.to.index() / 8))));
+		assert!(Position::default().drop_moves().is_empty());
+	}
+}
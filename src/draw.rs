@@ -0,0 +1,263 @@
+use std::fmt::{Display, Formatter};
+
+use crate::bitboard::Bitboard;
+use crate::moves::MoveKind;
+use crate::parser::{Fen, PieceColor, PieceType};
+use crate::position::Position;
+
+/// A rule that makes a game drawn, either on its own or when a player claims it
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum DrawReason {
+	/// The same position came up for the third time, which a player can claim as a draw
+	ThreefoldRepetition,
+	/// The same position came up for the fifth time, which ends the game
+	FivefoldRepetition,
+	/// Fifty moves by each side passed without a capture or pawn move, which a player can claim as a draw
+	FiftyMoves,
+	/// Seventy-five moves by each side passed without a capture or pawn move, which ends the game
+	SeventyFiveMoves,
+	/// Neither side has the pieces left to checkmate
+	InsufficientMaterial
+}
+
+impl DrawReason {
+	/// Whether the rule ends the game right away, instead of only when a player claims it
+	pub fn is_automatic(self) -> bool {
+		!matches!(self, DrawReason::ThreefoldRepetition | DrawReason::FiftyMoves)
+	}
+}
+
+impl Position {
+	/// How many times the position has come up, counting itself,
+	/// where `history` holds the positions before it in the game.
+	/// 
+	/// Positions count as the same when they have the same pieces on the same squares,
+	/// the same side to move, the same castling rights and the same en passant captures available
+	pub fn repetitions(&self, history: &[Position]) -> usize {
+		let key = self.repetition_key();
+		
+		1 + history.iter().filter(|position| position.repetition_key() == key).count()
+	}
+	
+	/// Whether the position has come up at least three times, see [`Position::repetitions`]
+	pub fn is_threefold_repetition(&self, history: &[Position]) -> bool {
+		self.repetitions(history) >= 3
+	}
+	
+	/// Whether the position has come up at least five times, see [`Position::repetitions`]
+	pub fn is_fivefold_repetition(&self, history: &[Position]) -> bool {
+		self.repetitions(history) >= 5
+	}
+	
+	/// Whether at least fifty moves by each side were played without a capture or pawn move
+	pub fn is_fifty_moves(&self) -> bool {
+		self.halfmove_clock >= 100
+	}
+	
+	/// Whether at least seventy-five moves by each side were played without a capture or pawn move,
+	/// unless the last of them was checkmate, which wins the game instead
+	pub fn is_seventy_five_moves(&self) -> bool {
+		self.halfmove_clock >= 150 && !self.is_checkmate()
+	}
+	
+	/// Whether neither side can checkmate by any sequence of legal moves,
+	/// like a lone king against a king and knight, or kings with bishops all on squares of one color
+	pub fn is_insufficient_material(&self) -> bool {
+		self.has_insufficient_material(PieceColor::White) && self.has_insufficient_material(PieceColor::Black)
+	}
+	
+	/// Whether the color cannot checkmate by any sequence of legal moves, even with help from the other side,
+	/// which decides whether running out of time loses or draws
+	pub fn has_insufficient_material(&self, color: PieceColor) -> bool {
+		let ours = self.by_color(color);
+		let theirs = self.by_color(color.opposite());
+		
+		// Every piece can be captured and dropped in crazyhouse, so only bare kings with empty hands cannot mate
+		if let Some(holdings) = self.holdings {
+			return holdings.is_empty() && self.occupied() == self.by_type(PieceType::King);
+		}
+		
+		if let Some(insufficient) = self.variant_insufficient_material(color) {
+			return insufficient;
+		}
+		
+		let heavy = self.by_type(PieceType::Pawn) | self.by_type(PieceType::Rook) | self.by_type(PieceType::Queen);
+		
+		if !(ours & heavy).is_empty() {
+			return false;
+		}
+		
+		if !(ours & self.by_type(PieceType::Knight)).is_empty() {
+			// A knight can only mate when the other king is boxed in by its own pieces, which queens never do
+			let blockers = theirs - self.by_type(PieceType::King) - self.by_type(PieceType::Queen);
+			return ours.count() <= 2 && blockers.is_empty();
+		}
+		
+		if !(ours & self.by_type(PieceType::Bishop)).is_empty() {
+			// Bishops on squares of one color can only mate with a knight or pawn blocking the other king
+			let bishops = self.by_type(PieceType::Bishop);
+			let one_color = (bishops & Bitboard::DARK_SQUARES).is_empty() || (bishops & Bitboard::LIGHT_SQUARES).is_empty();
+			return one_color && (self.by_type(PieceType::Pawn) | self.by_type(PieceType::Knight)).is_empty();
+		}
+		
+		true
+	}
+	
+	/// Every rule that draws the game in the position, where `history` holds the positions before it in the game
+	pub fn draw_reasons(&self, history: &[Position]) -> Vec<DrawReason> {
+		let repetitions = self.repetitions(history);
+		let mut reasons = Vec::new();
+		
+		if repetitions >= 3 {
+			reasons.push(DrawReason::ThreefoldRepetition);
+		}
+		if repetitions >= 5 {
+			reasons.push(DrawReason::FivefoldRepetition);
+		}
+		if self.is_fifty_moves() {
+			reasons.push(DrawReason::FiftyMoves);
+		}
+		if self.is_seventy_five_moves() {
+			reasons.push(DrawReason::SeventyFiveMoves);
+		}
+		if self.is_insufficient_material() {
+			reasons.push(DrawReason::InsufficientMaterial);
+		}
+		
+		reasons
+	}
+	
+	/// The position without its clocks, and without its en passant square when no en passant capture is legal,
+	/// so that positions compare equal exactly when they count as repetitions
+	fn repetition_key(&self) -> Position {
+		let mut key = *self;
+		key.halfmove_clock = 0;
+		key.fullmove_number = 1;
+		
+		if key.en_passant.is_some() && !self.legal_moves().iter().any(|mv| mv.kind == MoveKind::EnPassant) {
+			key.en_passant = None;
+			key.refresh_zobrist_hash();
+		}
+		
+		key
+	}
+}
+
+impl Fen {
+	/// How many times the position has come up, see [`Position::repetitions`]
+	pub fn repetitions(&self, history: &[Fen]) -> usize {
+		let history: Vec<Position> = history.iter().map(Position::from).collect();
+		Position::from(self).repetitions(&history)
+	}
+	
+	/// Whether neither side can checkmate, see [`Position::is_insufficient_material`]
+	pub fn is_insufficient_material(&self) -> bool {
+		Position::from(self).is_insufficient_material()
+	}
+	
+	/// Every rule that draws the game in the position, see [`Position::draw_reasons`]
+	pub fn draw_reasons(&self, history: &[Fen]) -> Vec<DrawReason> {
+		let history: Vec<Position> = history.iter().map(Position::from).collect();
+		Position::from(self).draw_reasons(&history)
+	}
+}
+
+impl Display for DrawReason {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			DrawReason::ThreefoldRepetition => write!(f, "threefold repetition"),
+			DrawReason::FivefoldRepetition => write!(f, "fivefold repetition"),
+			DrawReason::FiftyMoves => write!(f, "fifty-move rule"),
+			DrawReason::SeventyFiveMoves => write!(f, "seventy-five-move rule"),
+			DrawReason::InsufficientMaterial => write!(f, "insufficient material")
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	/// Plays the moves from the position, returning the position reached and every position before it
+	fn play(fen: &str, moves: &str) -> (Position, Vec<Position>) {
+		let mut position = Position::from(Fen::parse(fen).unwrap());
+		let mut history = Vec::new();
+		
+		for san in moves.split_whitespace() {
+			let mv = position.parse_san(san).unwrap();
+			history.push(position);
+			position.make_move(mv);
+		}
+		
+		(position, history)
+	}
+	
+	fn insufficient(fen: &str) -> bool {
+		Fen::parse(fen).unwrap().is_insufficient_material()
+	}
+	
+	#[test]
+	fn repetition() {
+		let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+		let (twice, history) = play(start, "Nf3 Nf6 Ng1 Ng8");
+		assert_eq!(twice.repetitions(&history), 2);
+		assert!(!twice.is_threefold_repetition(&history));
+		
+		let (thrice, history) = play(start, "Nf3 Nf6 Ng1 Ng8 Nf3 Nf6 Ng1 Ng8");
+		assert!(thrice.is_threefold_repetition(&history));
+		assert!(!thrice.is_fivefold_repetition(&history));
+		assert_eq!(thrice.draw_reasons(&history), [DrawReason::ThreefoldRepetition]);
+		
+		let (five, history) = play(start, "Nf3 Nf6 Ng1 Ng8 Nf3 Nf6 Ng1 Ng8 Nf3 Nf6 Ng1 Ng8 Nf3 Nf6 Ng1 Ng8");
+		assert!(five.is_fivefold_repetition(&history));
+		assert_eq!(five.draw_reasons(&history), [DrawReason::ThreefoldRepetition, DrawReason::FivefoldRepetition]);
+	}
+	
+	#[test]
+	fn repetition_with_en_passant_square() {
+		// Nothing can capture on e3, so the position after 1. e4 is the same as after the knights return
+		let (position, history) = play("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "e4 Nf6 Nf3 Ng8 Ng1 Nf6 Nf3 Ng8 Ng1");
+		assert_eq!(position.repetitions(&history), 3);
+		
+		// The d4 pawn can capture on e3 right after 1. e4, so that position is a different one
+		let (position, history) = play("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1", "e4 Ke7 Kf1 Ke8 Ke1");
+		assert_eq!(position.repetitions(&history), 1);
+		
+		let (position, history) = play("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1", "e4 Ke7 Kf1 Ke8 Ke1 Ke7 Kf1 Ke8 Ke1");
+		assert_eq!(position.repetitions(&history), 2);
+	}
+	
+	#[test]
+	fn move_rules() {
+		let at = |halfmove_clock: u32| Position::from(Fen::parse(&format!("4k3/8/8/8/8/8/8/R3K3 w - - {halfmove_clock} 80")).unwrap());
+		
+		assert!(!at(99).is_fifty_moves());
+		assert!(at(100).is_fifty_moves());
+		assert!(!at(149).is_seventy_five_moves());
+		assert!(at(150).is_seventy_five_moves());
+		assert_eq!(at(99).draw_reasons(&[]), []);
+		assert_eq!(at(100).draw_reasons(&[]), [DrawReason::FiftyMoves]);
+		assert_eq!(at(150).draw_reasons(&[]), [DrawReason::FiftyMoves, DrawReason::SeventyFiveMoves]);
+		assert!(!DrawReason::FiftyMoves.is_automatic() && DrawReason::SeventyFiveMoves.is_automatic());
+	}
+	
+	#[test]
+	fn insufficient_material() {
+		assert!(insufficient("4k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+		assert!(insufficient("4k3/8/8/8/8/8/8/4KN2 w - - 0 1"));
+		assert!(insufficient("4k3/8/8/8/8/8/8/4KB2 w - - 0 1"));
+		assert!(insufficient("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+		assert!(insufficient("4k3/8/8/8/8/8/3B4/B1B1K3 w - - 0 1"));
+		
+		assert!(!insufficient("4k1b1/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+		assert!(!insufficient("4k3/8/8/8/8/8/8/3NKN2 w - - 0 1"));
+		assert!(!insufficient("4k3/8/8/8/8/8/8/4KBN1 w - - 0 1"));
+		assert!(!insufficient("4kn2/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+		assert!(!insufficient("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+		assert!(!insufficient("4k3/8/8/8/8/8/8/R3K3 w - - 0 1"));
+		
+		let lone_knight = Position::from(Fen::parse("4k3/8/8/8/8/8/4p3/4KN2 w - - 0 1").unwrap());
+		assert!(!lone_knight.has_insufficient_material(PieceColor::White));
+		assert!(!lone_knight.has_insufficient_material(PieceColor::Black));
+	}
+}
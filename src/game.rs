@@ -0,0 +1,547 @@
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+
+use crate::draw::DrawReason;
+use crate::moves::Move;
+use crate::parser::Fen;
+use crate::pgn::{self, GameResult, MovetextWriter, PgnError, PgnGame, Token};
+use crate::position::Position;
+
+/// A game as a tree of moves, with a main line, side lines and annotations on every move.
+///
+/// Every move is a node identified by a [`NodeId`], and the root node stands for the starting position.
+/// The first child of a node continues its line, and any other children are variations of that move
+#[derive(Debug, Clone)]
+pub struct Game {
+	/// The tag pairs in the order they appear, like `("White", "Carlsen, Magnus")`
+	pub tags: Vec<(String, String)>,
+	pub result: Option<GameResult>,
+	nodes: Vec<Node>
+}
+
+/// A node in a [`Game`], which stays valid as long as the game does,
+/// even after its variation has been deleted
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone)]
+struct Node {
+	/// The move that leads to this node, `None` for the root
+	mv: Option<Move>,
+	/// The position after the move
+	position: Position,
+	parent: Option<NodeId>,
+	children: Vec<NodeId>,
+	/// The comment in front of the move, which PGN only has at the start of a variation
+	starting_comment: Option<String>,
+	/// The comment after the move, or before the first move for the root
+	comment: Option<String>,
+	nags: Vec<u8>
+}
+
+impl Game {
+	/// A game without any moves, tags or result, starting from the given position
+	pub fn new(start: Fen) -> Self {
+		Game {
+			tags: Vec::new(),
+			result: None,
+			nodes: vec![Node {
+				mv: None,
+				position: Position::from(start),
+				parent: None,
+				children: Vec::new(),
+				starting_comment: None,
+				comment: None,
+				nags: Vec::new()
+			}]
+		}
+	}
+	
+	/// The node of the starting position
+	pub fn root(&self) -> NodeId {
+		NodeId(0)
+	}
+	
+	/// The position before the first move
+	pub fn start(&self) -> Fen {
+		self.fen(self.root())
+	}
+	
+	/// The position at the node, after its move has been made
+	pub fn fen(&self, node: NodeId) -> Fen {
+		Fen::from(self.nodes[node.0].position)
+	}
+	
+	/// Every rule that draws the game at the node, counting repetitions of the positions on the way there
+	pub fn draw_reasons(&self, node: NodeId) -> Vec<DrawReason> {
+		let mut history = Vec::new();
+		let mut ancestor = self.parent(node);
+		
+		while let Some(parent) = ancestor {
+			history.push(self.nodes[parent.0].position);
+			ancestor = self.parent(parent);
+		}
+		
+		history.reverse();
+		self.nodes[node.0].position.draw_reasons(&history)
+	}
+	
+	/// The move that leads to the node, `None` for the root
+	pub fn move_at(&self, node: NodeId) -> Option<Move> {
+		self.nodes[node.0].mv
+	}
+	
+	/// The node before this one, `None` for the root
+	pub fn parent(&self, node: NodeId) -> Option<NodeId> {
+		self.nodes[node.0].parent
+	}
+	
+	/// The moves that can follow the node, the first one continuing its line and the others being variations
+	pub fn children(&self, node: NodeId) -> &[NodeId] {
+		&self.nodes[node.0].children
+	}
+	
+	/// The node after the main line's last move
+	pub fn end(&self) -> NodeId {
+		self.mainline().last().copied().unwrap_or(self.root())
+	}
+	
+	/// Every node of the main line after the root, in order
+	pub fn mainline(&self) -> Vec<NodeId> {
+		let mut nodes = Vec::new();
+		let mut node = self.root();
+		
+		while let Some(&next) = self.children(node).first() {
+			nodes.push(next);
+			node = next;
+		}
+		
+		nodes
+	}
+	
+	/// The moves of the main line, in order
+	pub fn mainline_moves(&self) -> Vec<Move> {
+		self.mainline()
+			.into_iter()
+			.filter_map(|node| self.move_at(node))
+			.collect()
+	}
+	
+	/// Adds a move after the node and returns its new node,
+	/// or returns the existing node if the move is already there.
+	///
+	/// The first move added after a node continues its line and any later ones become variations.
+	/// The move is not checked for legality, see [`Position::is_legal`]
+	pub fn add_move(&mut self, node: NodeId, mv: Move) -> NodeId {
+		if let Some(&existing) = self.children(node).iter().find(|&&child| self.move_at(child) == Some(mv)) {
+			return existing;
+		}
+		
+		let child = NodeId(self.nodes.len());
+		self.nodes.push(Node {
+			mv: Some(mv),
+			position: self.nodes[node.0].position.play(mv),
+			parent: Some(node),
+			children: Vec::new(),
+			starting_comment: None,
+			comment: None,
+			nags: Vec::new()
+		});
+		self.nodes[node.0].children.push(child);
+		
+		child
+	}
+	
+	pub fn comment(&self, node: NodeId) -> Option<&str> {
+		self.nodes[node.0].comment.as_deref()
+	}
+	
+	/// Sets the comment after the node's move, or before the first move for the root
+	pub fn set_comment(&mut self, node: NodeId, comment: Option<String>) {
+		self.nodes[node.0].comment = comment;
+	}
+	
+	pub fn starting_comment(&self, node: NodeId) -> Option<&str> {
+		self.nodes[node.0].starting_comment.as_deref()
+	}
+	
+	/// Sets the comment in front of the node's move, which is only written for the first move of a variation
+	pub fn set_starting_comment(&mut self, node: NodeId, comment: Option<String>) {
+		self.nodes[node.0].starting_comment = comment;
+	}
+	
+	/// The numeric annotation glyphs of the node's move, like 1 for `!` or 14 for a slight advantage for White
+	pub fn nags(&self, node: NodeId) -> &[u8] {
+		&self.nodes[node.0].nags
+	}
+	
+	pub fn add_nag(&mut self, node: NodeId, nag: u8) {
+		if !self.nodes[node.0].nags.contains(&nag) {
+			self.nodes[node.0].nags.push(nag);
+		}
+	}
+	
+	pub fn remove_nag(&mut self, node: NodeId, nag: u8) {
+		self.nodes[node.0].nags.retain(|&other| other != nag);
+	}
+	
+	/// Moves the variation starting at the node one place up among the moves after its parent,
+	/// which makes it the main line if it was the first variation
+	pub fn promote_variation(&mut self, node: NodeId) {
+		if let Some((parent, index)) = self.sibling_index(node) {
+			if index > 0 {
+				self.nodes[parent.0].children.swap(index, index - 1);
+			}
+		}
+	}
+	
+	/// Moves the variation starting at the node to the front of the moves after its parent, making it the main line
+	pub fn promote_to_mainline(&mut self, node: NodeId) {
+		if let Some((parent, index)) = self.sibling_index(node) {
+			let children = &mut self.nodes[parent.0].children;
+			let child = children.remove(index);
+			children.insert(0, child);
+		}
+	}
+	
+	/// Moves the variation starting at the node one place down among the moves after its parent
+	pub fn demote_variation(&mut self, node: NodeId) {
+		if let Some((parent, index)) = self.sibling_index(node) {
+			let children = &mut self.nodes[parent.0].children;
+			if index + 1 < children.len() {
+				children.swap(index, index + 1);
+			}
+		}
+	}
+	
+	/// Removes the node and every move after it from the tree.
+	/// Deleting the root removes every move of the game.
+	/// 
+	/// The removed nodes keep their place in memory so that every [`NodeId`] stays valid,
+	/// but they no longer take part in comparing or hashing the game
+	pub fn delete_variation(&mut self, node: NodeId) {
+		match self.sibling_index(node) {
+			Some((parent, index)) => {
+				self.nodes[parent.0].children.remove(index);
+			}
+			None => self.nodes[node.0].children.clear()
+		}
+	}
+	
+	/// The parent of the node and the node's place among the parent's children
+	fn sibling_index(&self, node: NodeId) -> Option<(NodeId, usize)> {
+		let parent = self.parent(node)?;
+		let index = self.children(parent).iter().position(|&child| child == node)?;
+		Some((parent, index))
+	}
+	
+	/// Reads the first game from PGN text, keeping its variations, comments and NAGs
+	pub fn from_pgn(text: &str) -> Result<Self, PgnError> {
+		let tokens = pgn::tokenize(text, 1)?;
+		let (tags, start) = pgn::read_tags(&tokens)?;
+		
+		let mut game = Game::new(start);
+		game.tags = tags;
+		
+		// The node after the last move, and the node to go back to when each open variation ends
+		let mut current = game.root();
+		let mut variations = Vec::new();
+		let mut starting_comment = None;
+		let mut in_movetext = false;
+		
+		for (line, token) in tokens {
+			match token {
+				// A variation that is still open when the movetext ends
+				Token::Tag(..) | Token::Result(_) if !variations.is_empty() => return Err(PgnError::Syntax {
+					line
+				}),
+				// The tag pairs of the next game
+				Token::Tag(..) if in_movetext => break,
+				Token::Tag(..) => continue,
+				Token::Move(san) => {
+					let mv = game.nodes[current.0].position.parse_san(&san).map_err(|error| PgnError::InvalidMove {
+						line,
+						san,
+						error
+					})?;
+					
+					current = game.add_move(current, mv);
+					if starting_comment.is_some() {
+						game.nodes[current.0].starting_comment = starting_comment.take();
+					}
+				}
+				Token::Comment(comment) => {
+					let at_variation_start = variations.last().is_some_and(|&(_, start)| start == current);
+					
+					if at_variation_start {
+						starting_comment = Some(comment);
+					} else {
+						let existing = &mut game.nodes[current.0].comment;
+						*existing = Some(match existing.take() {
+							Some(existing) => format!("{existing} {comment}"),
+							None => comment
+						});
+					}
+				}
+				Token::Nag(nag) => game.add_nag(current, nag),
+				Token::VariationStart => {
+					let parent = game.parent(current).ok_or(PgnError::Syntax {
+						line
+					})?;
+					
+					variations.push((current, parent));
+					current = parent;
+				}
+				Token::VariationEnd => {
+					let (resume, _) = variations.pop().ok_or(PgnError::Syntax {
+						line
+					})?;
+					
+					current = resume;
+				}
+				Token::Result(result) => game.result = Some(result),
+			}
+			
+			in_movetext = true;
+		}
+		
+		if !variations.is_empty() {
+			return Err(PgnError::Syntax {
+				line: text.lines().count()
+			});
+		}
+		
+		Ok(game)
+	}
+	
+	/// Writes the game in PGN export format, with every variation, comment and NAG
+	pub fn to_pgn(&self) -> String {
+		let result = self.result.unwrap_or(GameResult::Unknown);
+		let mut pgn = pgn::write_tags(&self.tags, &self.start(), result);
+		let mut movetext = MovetextWriter::new();
+		
+		if let Some(comment) = self.comment(self.root()) {
+			movetext.push_comment(comment);
+		}
+		
+		self.write_line(self.root(), &mut movetext);
+		movetext.push_token(&result.to_string());
+		
+		pgn.push_str(&movetext.finish());
+		pgn
+	}
+	
+	/// Writes the moves after the node, with the variations of every move right after it
+	fn write_line(&self, node: NodeId, movetext: &mut MovetextWriter) {
+		let mut node = node;
+		
+		while let Some((&main, variations)) = self.children(node).split_first() {
+			self.write_move(main, movetext);
+			
+			for &variation in variations {
+				movetext.open_variation();
+				self.write_move(variation, movetext);
+				self.write_line(variation, movetext);
+				movetext.close_variation();
+			}
+			
+			node = main;
+		}
+	}
+	
+	fn write_move(&self, node: NodeId, movetext: &mut MovetextWriter) {
+		let Some(mv) = self.move_at(node) else {
+			return;
+		};
+		let before = self.parent(node).map_or(self.nodes[node.0].position, |parent| self.nodes[parent.0].position);
+		
+		if let Some(comment) = self.starting_comment(node) {
+			movetext.push_comment(comment);
+		}
+		
+		movetext.push_move(&before, mv);
+		
+		for &nag in self.nags(node) {
+			movetext.push_nag(nag);
+		}
+		
+		if let Some(comment) = self.comment(node) {
+			movetext.push_comment(comment);
+		}
+	}
+}
+
+impl From<&PgnGame> for Game {
+	fn from(pgn_game: &PgnGame) -> Self {
+		let mut game = Game::new(pgn_game.start);
+		game.tags.clone_from(&pgn_game.tags);
+		game.result = pgn_game.result;
+		
+		let mut node = game.root();
+		for &mv in &pgn_game.moves {
+			node = game.add_move(node, mv);
+		}
+		
+		game
+	}
+}
+
+impl From<&Game> for PgnGame {
+	/// The main line of the game, without variations or annotations
+	fn from(game: &Game) -> Self {
+		PgnGame {
+			tags: game.tags.clone(),
+			start: game.start(),
+			moves: game.mainline_moves(),
+			result: game.result
+		}
+	}
+}
+
+impl Display for Game {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.to_pgn())
+	}
+}
+
+/// Two games are equal when they have the same tags, result, starting position, moves and annotations,
+/// walking the tree from the root so that deleted variations do not count
+impl PartialEq for Game {
+	fn eq(&self, other: &Self) -> bool {
+		if self.tags != other.tags || self.result != other.result || self.nodes[self.root().0].position != other.nodes[other.root().0].position {
+			return false;
+		}
+		
+		let mut pairs = vec![(self.root(), other.root())];
+		
+		while let Some((node, other_node)) = pairs.pop() {
+			let (node, other_node) = (&self.nodes[node.0], &other.nodes[other_node.0]);
+			
+			if node.mv != other_node.mv
+				|| node.starting_comment != other_node.starting_comment
+				|| node.comment != other_node.comment
+				|| node.nags != other_node.nags
+				|| node.children.len() != other_node.children.len()
+			{
+				return false;
+			}
+			
+			pairs.extend(node.children.iter().copied().zip(other_node.children.iter().copied()));
+		}
+		
+		true
+	}
+}
+
+impl Eq for Game {}
+
+/// Hashes what [`PartialEq`] compares, walking the tree from the root
+impl Hash for Game {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.tags.hash(state);
+		self.result.hash(state);
+		self.nodes[self.root().0].position.hash(state);
+		
+		let mut nodes = vec![self.root()];
+		
+		while let Some(node) = nodes.pop() {
+			let node = &self.nodes[node.0];
+			
+			node.mv.hash(state);
+			node.starting_comment.hash(state);
+			node.comment.hash(state);
+			node.nags.hash(state);
+			node.children.len().hash(state);
+			nodes.extend(node.children.iter().copied());
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	const ANNOTATED: &str = r#"[Event "Casual game"]
+[Site "London"]
+[Date "1851.06.21"]
+[Round "?"]
+[White "Anderssen, Adolf"]
+[Black "Kieseritzky, Lionel"]
+[Result "1-0"]
+
+{Opening comment} 1. e4 {King pawn} e5 2. f4 $1 (2. Nf3 Nc6 (2... d6 {Philidor}) 3. Bb5 $2) 2... exf4
+3. Bc4 Qh4+ 4. Kf1 b5 $5 (4... d6 5. Nc3) 5. Bxb5 Nf6 1-0
+"#;
+	
+	#[test]
+	fn unclosed_variation() {
+		let before_result = "1. e4 e5 (1... c5 (1... e6) 2. Nf3 1-0";
+		let at_end = "[Event \"?\"]\n\n1. e4 e5\n(1... c5 2. Nf3\n";
+		
+		assert!(matches!(Game::from_pgn(before_result), Err(PgnError::Syntax { line: 1 })));
+		assert!(matches!(Game::from_pgn(at_end), Err(PgnError::Syntax { line: 4 })));
+	}
+	
+	#[test]
+	fn several_games() {
+		let text = "[Event \"First\"]\n\n1. e4 e5 1-0\n\n[Event \"Second\"]\n[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]\n\n1. O-O *\n";
+		let game = Game::from_pgn(text).unwrap();
+		
+		assert_eq!(game.tags, [("Event".to_string(), "First".to_string())]);
+		assert_eq!(game.start(), Fen::default());
+		assert_eq!(game.mainline_moves().len(), 2);
+		assert_eq!(game.result, Some(GameResult::WhiteWins));
+	}
+	
+	#[test]
+	fn edit_variations() {
+		let mut game = Game::from_pgn("1. e4 (1. d4) (1. c4) e5 *").unwrap();
+		let [e4, d4, c4] = game.children(game.root()).try_into().unwrap();
+		
+		game.promote_variation(c4);
+		assert_eq!(game.children(game.root()), [e4, c4, d4]);
+		game.promote_variation(e4);
+		assert_eq!(game.children(game.root()), [e4, c4, d4]);
+		game.demote_variation(e4);
+		assert_eq!(game.children(game.root()), [c4, e4, d4]);
+		game.demote_variation(d4);
+		assert_eq!(game.children(game.root()), [c4, e4, d4]);
+		game.promote_to_mainline(d4);
+		assert_eq!(game.children(game.root()), [d4, c4, e4]);
+		assert_eq!(game.mainline(), [d4]);
+		
+		game.delete_variation(c4);
+		assert_eq!(game.children(game.root()), [d4, e4]);
+		assert_eq!(game, Game::from_pgn("1. d4 (1. e4 e5) *").unwrap());
+		
+		game.delete_variation(e4);
+		assert_eq!(game, Game::from_pgn("1. d4 *").unwrap());
+		assert_eq!(game.parent(e4), Some(game.root()));
+		
+		game.delete_variation(game.root());
+		assert!(game.children(game.root()).is_empty());
+		assert_eq!(game, Game::from_pgn("*").unwrap());
+	}
+	
+	#[test]
+	fn round_trip() {
+		let game = Game::from_pgn(ANNOTATED).unwrap();
+		let written = game.to_pgn();
+		let reread = Game::from_pgn(&written).unwrap();
+		
+		assert_eq!(reread, game);
+		assert_eq!(reread.to_pgn(), written);
+		
+		let e4 = game.children(game.root())[0];
+		let f4 = game.mainline()[2];
+		let nf3 = game.children(game.parent(f4).unwrap())[1];
+		let d6 = game.children(nf3)[1];
+		
+		assert_eq!(game.comment(game.root()), Some("Opening comment"));
+		assert_eq!(game.comment(e4), Some("King pawn"));
+		assert_eq!(game.nags(f4), [1]);
+		assert_eq!(game.comment(d6), Some("Philidor"));
+		assert_eq!(game.mainline_moves().len(), 10);
+		assert!(written.contains("2. f4 $1 (2. Nf3 Nc6 (2... d6") && written.contains("(4... d6 5. Nc3)"));
+	}
+}
@@ -0,0 +1,19 @@
+pub mod bitboard;
+pub mod chess960;
+pub mod crazyhouse;
+pub mod draw;
+pub mod game;
+pub mod moves;
+pub mod parser;
+pub mod perft;
+pub mod pgn;
+pub mod polyglot;
+pub mod position;
+pub mod random;
+pub mod san;
+pub mod square;
+pub mod status;
+pub mod uci;
+pub mod validate;
+pub mod variant;
+pub mod zobrist;
@@ -1,8 +1,21 @@
-use std::fs;
+use std::{env, fs};
 
-mod parser;
+use fen_chess_notation_decoder::parser;
+use fen_chess_notation_decoder::perft;
+use fen_chess_notation_decoder::position::Position;
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("perft") => run_perft(&args[1..], false),
+        Some("divide") => run_perft(&args[1..], true),
+        Some("perft-suite") => run_perft_suite(&args[1..]),
+        _ => print_examples()
+    }
+}
+
+fn print_examples() {
     let starting_position_file =
         fs::read_to_string("fen_starting_position.txt")
             .unwrap_or_default();
@@ -19,9 +32,10 @@ fn main() {
         fs::read_to_string("../fen_chess.com_game.txt")
             .unwrap_or_default();
 
-    let starting_position = parser::Fen::from_string(starting_position_file_stripped.as_str());
-    let variety_test = parser::Fen::from_string(variety_test_file.as_str());
-    let chess_com_game = parser::Fen::from_string(chess_com_game_file.as_str());
+    let starting_position = parser::Fen::parse(starting_position_file.as_str());
+    let starting_position_stripped = parser::Fen::parse(starting_position_file_stripped.as_str());
+    let variety_test = parser::Fen::parse(variety_test_file.as_str());
+    let chess_com_game = parser::Fen::parse(chess_com_game_file.as_str());
 
     println!("--- WIKI EXAMPLE: STARTING POSITION ---");
     println!("{starting_position_file}");
@@ -39,14 +53,67 @@ fn main() {
     println!("{}", parser::Fen::default());
 
     println!("--- FEN: STARTING POSITION ---");
-    println!("{starting_position}");
+    print_parsed(&starting_position);
+
+    println!("--- FEN: STARTING POSITION STRIPPED ---");
+    print_parsed(&starting_position_stripped);
 
     println!("--- FEN: VARIETY TEST ---");
-    println!("{variety_test}");
+    print_parsed(&variety_test);
 
     println!("--- FEN: CHESS.COM GAME ---");
-    println!("{chess_com_game}");
+    print_parsed(&chess_com_game);
 
     println!("--- ROW: EMPTY ---");
     println!("{}", parser::Row::empty());
 }
+
+fn print_parsed(fen: &Result<parser::Fen, parser::FenError>) {
+    match fen {
+        Ok(fen) => println!("{fen}"),
+        Err(error) => println!("Invalid FEN notation: {error}")
+    }
+}
+
+/// Runs `perft <depth> [fen]` or `divide <depth> [fen]`, using the starting position if no FEN is given
+fn run_perft(args: &[String], divide: bool) {
+    let depth = args.first().and_then(|depth| depth.parse().ok()).unwrap_or(1);
+    let fen = if args.len() > 1 {
+        match parser::Fen::parse(&args[1..].join(" ")) {
+            Ok(fen) => fen,
+            Err(error) => {
+                println!("Invalid FEN notation: {error}");
+                return;
+            }
+        }
+    } else {
+        parser::Fen::default()
+    };
+    let position = Position::from(fen);
+
+    if divide {
+        let mut total = 0;
+
+        for (mv, nodes) in perft::divide(&position, depth) {
+            println!("{mv}: {nodes}");
+            total += nodes;
+        }
+
+        println!();
+        println!("Nodes searched: {total}");
+    } else {
+        println!("Nodes searched: {}", perft::perft(&position, depth));
+    }
+}
+
+/// Runs `perft-suite [max depth]`, checking every reference position up to the given depth
+fn run_perft_suite(args: &[String]) {
+    let max_depth = args.first().and_then(|depth| depth.parse().ok()).unwrap_or(4);
+
+    for reference in perft::REFERENCE_POSITIONS {
+        match reference.verify(max_depth) {
+            Ok(()) => println!("{}: ok", reference.name),
+            Err(mismatch) => println!("{mismatch}")
+        }
+    }
+}
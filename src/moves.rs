@@ -0,0 +1,631 @@
+use std::fmt::{Display, Formatter};
+
+use crate::bitboard::{self, Bitboard};
+use crate::crazyhouse::Holdings;
+use crate::parser::{CastlingRights, Fen, Piece, PieceColor, PieceType};
+use crate::position::{self, Position};
+use crate::square::{File, Rank, Square};
+use crate::variant::Variant;
+
+/// A move of one piece, described by the square it leaves and the square it lands on
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Move {
+	pub from: Square,
+	/// The square the moving piece lands on, which for castling is the square the king lands on
+	pub to: Square,
+	pub kind: MoveKind
+}
+
+/// What else happens on the board besides a piece moving from one square to another
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum MoveKind {
+	/// A quiet move or a capture on the target square, including pawn double steps
+	Normal,
+	/// A pawn reaching the last rank and turning into the given piece type
+	Promotion(PieceType),
+	/// A pawn capturing a pawn that just passed it with a double step
+	EnPassant,
+	/// The king moving two files towards a rook, which jumps over to the king's other side
+	Castle(CastlingSide),
+	/// A held piece of the given type put on the empty target square in crazyhouse,
+	/// where the move's `from` is the same as its `to`
+	Drop(PieceType)
+}
+
+/// What [`Position::unmake_move`] needs to take back a move, returned by [`Position::make_move`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Undo {
+	/// The piece the move captured, which is [`Piece::air`] if it captured nothing
+	pub captured: Piece,
+	pub castling: CastlingRights,
+	pub en_passant: Option<Square>,
+	pub halfmove_clock: u32,
+	pub fullmove_number: u32,
+	pub holdings: Option<Holdings>,
+	pub promoted: Bitboard,
+	pub checks_given: [u8; 2],
+	/// The pieces an atomic capture destroyed, the one on the target square first
+	/// and then the ones next to it in square order, all [`Piece::air`] for any other move
+	pub exploded: [Piece; 9],
+	/// The [`Position::zobrist_hash`] from before the move
+	pub hash: u64
+}
+
+/// The side of the board a castling move happens on
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum CastlingSide {
+	/// Towards file H, ending with the king on file G and the rook on file F
+	KingSide,
+	/// Towards file A, ending with the king on file C and the rook on file D
+	QueenSide
+}
+
+/// The piece types a pawn can promote to, from the most to the least valuable
+pub const PROMOTION_TYPES: [PieceType; 4] = [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight];
+
+impl Move {
+	pub fn new(from: Square, to: Square) -> Self {
+		Move {
+			from,
+			to,
+			kind: MoveKind::Normal
+		}
+	}
+	
+	/// A crazyhouse drop of a held piece of the given type onto the target square
+	pub fn new_drop(piece_type: PieceType, to: Square) -> Self {
+		Move {
+			from: to,
+			to,
+			kind: MoveKind::Drop(piece_type)
+		}
+	}
+}
+
+impl CastlingSide {
+	/// The file the king lands on when castling to this side
+	pub fn king_file(self) -> File {
+		match self {
+			CastlingSide::KingSide => File::G,
+			CastlingSide::QueenSide => File::C
+		}
+	}
+	
+	/// The file the rook lands on when castling to this side
+	pub fn rook_file(self) -> File {
+		match self {
+			CastlingSide::KingSide => File::F,
+			CastlingSide::QueenSide => File::D
+		}
+	}
+}
+
+impl Position {
+	/// Every move the side to move can make without looking at whether it leaves their own king in check.
+	///
+	/// Castling moves are only included if the king is not in check and does not pass through
+	/// or land on an attacked square, since that is part of how castling moves.
+	/// Drops are included when the position has crazyhouse holdings
+	pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+		let mut moves = Vec::with_capacity(64);
+		let us = self.side_to_move;
+		let own = self.by_color(us);
+		let occupied = self.occupied();
+		
+		for from in own {
+			let piece = self.piece_at(from);
+			
+			let targets = match piece.piece_type {
+				PieceType::Pawn => {
+					self.push_pawn_moves(from, &mut moves);
+					continue;
+				}
+				PieceType::Rook => bitboard::rook_attacks(from, occupied),
+				PieceType::Knight => bitboard::knight_attacks(from),
+				PieceType::Bishop => bitboard::bishop_attacks(from, occupied),
+				PieceType::Queen => bitboard::queen_attacks(from, occupied),
+				PieceType::King => bitboard::king_attacks(from),
+				PieceType::Empty => Bitboard::EMPTY
+			};
+			
+			for to in targets - own {
+				moves.push(Move::new(from, to));
+			}
+		}
+		
+		for side in [CastlingSide::KingSide, CastlingSide::QueenSide] {
+			if let Some(castle) = self.castling_move(side) {
+				moves.push(castle);
+			}
+		}
+		
+		moves.extend(self.drop_moves());
+		moves
+	}
+	
+	/// Every move the side to move can make that does not leave their own king in check,
+	/// following the rules of the position's [`Variant`], and none once a rule of the variant ended the game
+	pub fn legal_moves(&self) -> Vec<Move> {
+		if self.is_variant_end() {
+			return Vec::new();
+		}
+		
+		let mut moves = self.pseudo_legal_moves();
+		moves.retain(|&mv| self.leaves_king_safe(mv));
+		
+		if self.variant == Variant::Antichess && moves.iter().any(|&mv| self.is_capture(mv)) {
+			moves.retain(|&mv| self.is_capture(mv));
+		}
+		
+		moves
+	}
+	
+	/// Whether the move is one of [`Position::legal_moves`]
+	pub fn is_legal(&self, mv: Move) -> bool {
+		match self.variant {
+			Variant::Antichess | Variant::RacingKings => self.legal_moves().contains(&mv),
+			_ => self.pseudo_legal_moves().contains(&mv) && self.leaves_king_safe(mv) && !self.is_variant_end()
+		}
+	}
+	
+	/// The square of the rook that castles with the king on the given side
+	/// for the given color, if that castling right is still available
+	pub fn castling_rook(&self, color: PieceColor, side: CastlingSide) -> Option<Square> {
+		self.castling.rook_square(color, side)
+	}
+	
+	/// The castling move to the given side for the side to move,
+	/// if the right is available, the squares between king and rook are empty,
+	/// and the king is not in check and does not pass through or land on an attacked square
+	fn castling_move(&self, side: CastlingSide) -> Option<Move> {
+		let us = self.side_to_move;
+		let rook = self.castling_rook(us, side).filter(|_| self.variant != Variant::Antichess)?;
+		let king = self.king(us)?;
+		
+		if king.rank() != rook.rank() || self.piece_at(rook) != Piece::new(PieceType::Rook, us) {
+			return None;
+		}
+		
+		let king_to = Square::new(side.king_file(), king.rank());
+		let rook_to = Square::new(side.rook_file(), king.rank());
+		
+		let king_path = bitboard::between(king, king_to) | Bitboard::from(king_to);
+		let rook_path = bitboard::between(rook, rook_to) | Bitboard::from(rook_to);
+		let movers = Bitboard::from(king) | Bitboard::from(rook);
+		
+		if !((king_path | rook_path) & (self.occupied() - movers)).is_empty() {
+			return None;
+		}
+		
+		let them = us.opposite();
+		// In Atomic the other king cannot attack the squares next to it, since capturing there would blow it up too
+		let next_to_their_king = match (self.variant, self.king(them)) {
+			(Variant::Atomic, Some(theirs)) => bitboard::king_attacks(theirs),
+			_ => Bitboard::EMPTY
+		};
+		
+		// The squares the king crosses are looked at with the rook still in place, and the square it lands on with the rook moved
+		let crossing = self.occupied() - Bitboard::from(king);
+		let landing = (crossing - Bitboard::from(rook)) | Bitboard::from(rook_to);
+		let attacked = |square: Square, occupied: Bitboard| {
+			!next_to_their_king.contains(square) && !self.attackers_with(square, them, occupied | Bitboard::from(square)).is_empty()
+		};
+		
+		let crossed = bitboard::between(king, king_to) | Bitboard::from(king);
+		if crossed.into_iter().any(|square| attacked(square, crossing)) || attacked(king_to, landing) {
+			return None;
+		}
+		
+		Some(Move {
+			from: king,
+			to: king_to,
+			kind: MoveKind::Castle(side)
+		})
+	}
+	
+	fn push_pawn_moves(&self, from: Square, moves: &mut Vec<Move>) {
+		let us = self.side_to_move;
+		let occupied = self.occupied();
+		let (forward, first_rank, start_rank, last_rank) = match us {
+			PieceColor::Black => (-1, Rank::Eight, Rank::Seven, Rank::One),
+			_ => (1, Rank::One, Rank::Two, Rank::Eight)
+		};
+		// Horde pawns on the first rank can make a double step too
+		let double_step = from.rank() == start_rank || (self.variant == Variant::Horde && from.rank() == first_rank);
+		let king_promotion = (self.variant == Variant::Antichess).then_some(PieceType::King);
+		
+		let mut push = |to: Square| {
+			if to.rank() == last_rank {
+				for piece_type in PROMOTION_TYPES.into_iter().chain(king_promotion) {
+					moves.push(Move {
+						from,
+						to,
+						kind: MoveKind::Promotion(piece_type)
+					});
+				}
+			} else {
+				moves.push(Move::new(from, to));
+			}
+		};
+		
+		if let Some(single) = from.offset(0, forward).filter(|&to| !occupied.contains(to)) {
+			push(single);
+			
+			if double_step {
+				if let Some(double) = single.offset(0, forward).filter(|&to| !occupied.contains(to)) {
+					push(double);
+				}
+			}
+		}
+		
+		let attacks = bitboard::pawn_attacks(us, from);
+		
+		for to in attacks & self.by_color(us.opposite()) {
+			push(to);
+		}
+		
+		let captures_en_passant = |target: Square| {
+			attacks.contains(target)
+				&& !occupied.contains(target)
+				&& self.piece_at(Square::new(target.file(), from.rank())) == Piece::new(PieceType::Pawn, us.opposite())
+		};
+		
+		if let Some(target) = self.en_passant.filter(|&target| captures_en_passant(target)) {
+			moves.push(Move {
+				from,
+				to: target,
+				kind: MoveKind::EnPassant
+			});
+		}
+	}
+	
+	/// Whether the own king is safe from attack after making the move, true if the side to move has no king at all.
+	/// 
+	/// The variant changes what safe means: any move is fine in Antichess, no move may give check in Racing Kings,
+	/// and in Atomic the own king must survive the explosion, while blowing up the other king
+	/// or standing next to it is always safe
+	pub(crate) fn leaves_king_safe(&self, mv: Move) -> bool {
+		let us = self.side_to_move;
+		let them = us.opposite();
+		let after = self.with_pieces_moved(mv);
+		let is_safe = |color: PieceColor| match after.king(color) {
+			Some(king) => after.attackers(king, color.opposite()).is_empty(),
+			None => true
+		};
+		
+		match self.variant {
+			Variant::Antichess => true,
+			Variant::RacingKings => is_safe(us) && is_safe(them),
+			Variant::Atomic => match (after.king(us), after.king(them)) {
+				(None, _) => false,
+				(Some(_), None) => true,
+				(Some(ours), Some(theirs)) => bitboard::king_attacks(ours).contains(theirs) || is_safe(us)
+			},
+			_ => is_safe(us)
+		}
+	}
+	
+	/// Makes the move on the board and updates the side to move, castling rights,
+	/// en passant square and both clocks, returning what [`Position::unmake_move`] needs to take it back.
+	/// 
+	/// The move is not checked for legality, see [`Position::is_legal`]
+	pub fn make_move(&mut self, mv: Move) -> Undo {
+		let us = self.side_to_move;
+		let piece = match mv.kind {
+			MoveKind::Drop(piece_type) => Piece::new(piece_type, us),
+			_ => self.piece_at(mv.from)
+		};
+		let captured_square = match mv.kind {
+			MoveKind::EnPassant => Square::new(mv.to.file(), mv.from.rank()),
+			_ => mv.to
+		};
+		let captured = match mv.kind {
+			MoveKind::Castle(_) | MoveKind::Drop(_) => Piece::air(),
+			_ => self.piece_at(captured_square)
+		};
+		
+		let undo = Undo {
+			captured,
+			castling: self.castling,
+			en_passant: self.en_passant,
+			halfmove_clock: self.halfmove_clock,
+			fullmove_number: self.fullmove_number,
+			holdings: self.holdings,
+			promoted: self.promoted,
+			checks_given: self.checks_given,
+			exploded: self.explosion(mv),
+			hash: self.zobrist_hash()
+		};
+		
+		// The castling, en passant and turn keys come out of the hash here and go back in at the end,
+		// which leaves only the ones that changed
+		self.hash ^= self.state_key();
+		
+		for color in [PieceColor::White, PieceColor::Black] {
+			for side in [CastlingSide::KingSide, CastlingSide::QueenSide] {
+				let rook_touched = self
+					.castling_rook(color, side)
+					.is_some_and(|rook| rook == mv.from || rook == mv.to);
+				let king_moved = piece == Piece::new(PieceType::King, color);
+				
+				if rook_touched || king_moved {
+					self.castling.set(color, side, None);
+				}
+			}
+		}
+		
+		self.move_pieces(mv, &undo);
+		self.update_holdings(mv, captured, captured_square, &undo);
+		
+		if undo.exploded[0] != Piece::air() {
+			self.remove_exploded_castling();
+		}
+		
+		self.en_passant = None;
+		if piece.piece_type == PieceType::Pawn
+			&& mv.from.rank().index().abs_diff(mv.to.rank().index()) == 2
+			&& matches!(mv.from.rank(), Rank::Two | Rank::Seven)
+		{
+			self.en_passant = Square::from_index((mv.from.index() + mv.to.index()) / 2);
+		}
+		
+		if piece.piece_type == PieceType::Pawn || captured.piece_type != PieceType::Empty {
+			self.halfmove_clock = 0;
+		} else {
+			self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+		}
+		
+		if us == PieceColor::Black {
+			self.fullmove_number = self.fullmove_number.saturating_add(1);
+		}
+		self.side_to_move = us.opposite();
+		
+		if self.variant == Variant::ThreeCheck && self.is_check() {
+			if let Some(index) = position::color_index(us) {
+				self.checks_given[index] = self.checks_given[index].saturating_add(1);
+			}
+		}
+		
+		self.hash ^= self.state_key();
+		undo
+	}
+	
+	/// Takes away the castling rights whose king or rook an atomic explosion destroyed
+	fn remove_exploded_castling(&mut self) {
+		for color in [PieceColor::White, PieceColor::Black] {
+			for side in [CastlingSide::KingSide, CastlingSide::QueenSide] {
+				let rook_gone = self
+					.castling_rook(color, side)
+					.is_some_and(|rook| self.piece_at(rook) != Piece::new(PieceType::Rook, color));
+				
+				if rook_gone || self.king(color).is_none() {
+					self.castling.set(color, side, None);
+				}
+			}
+		}
+	}
+	
+	/// Takes back a move made with [`Position::make_move`], restoring the position exactly as it was
+	pub fn unmake_move(&mut self, mv: Move, undo: Undo) {
+		let us = self.side_to_move.opposite();
+		
+		self.side_to_move = us;
+		self.castling = undo.castling;
+		self.en_passant = undo.en_passant;
+		self.halfmove_clock = undo.halfmove_clock;
+		self.fullmove_number = undo.fullmove_number;
+		self.holdings = undo.holdings;
+		self.promoted = undo.promoted;
+		self.checks_given = undo.checks_given;
+		
+		if undo.exploded[0] != Piece::air() {
+			self.restore_explosion(mv.to, &undo.exploded);
+		}
+		
+		let piece = self.piece_at(mv.to);
+		
+		match mv.kind {
+			MoveKind::Normal => {
+				self.set_piece(mv.to, undo.captured);
+				self.set_piece(mv.from, piece);
+			}
+			MoveKind::Promotion(_) => {
+				self.set_piece(mv.to, undo.captured);
+				self.set_piece(mv.from, Piece::new(PieceType::Pawn, us));
+			}
+			MoveKind::EnPassant => {
+				self.set_piece(mv.to, Piece::air());
+				self.set_piece(Square::new(mv.to.file(), mv.from.rank()), undo.captured);
+				self.set_piece(mv.from, piece);
+			}
+			MoveKind::Castle(side) => {
+				let rook_to = Square::new(side.rook_file(), mv.to.rank());
+				let rook = self.castling_rook(us, side).unwrap_or(rook_to);
+				let rook_piece = self.piece_at(rook_to);
+				
+				self.set_piece(mv.to, Piece::air());
+				self.set_piece(rook_to, Piece::air());
+				self.set_piece(rook, rook_piece);
+				self.set_piece(mv.from, piece);
+			}
+			MoveKind::Drop(_) => self.set_piece(mv.to, Piece::air())
+		}
+		
+		self.hash = undo.hash;
+	}
+	
+	/// A copy of the position after making the move, see [`Position::make_move`]
+	pub fn play(&self, mv: Move) -> Position {
+		let mut after = *self;
+		after.make_move(mv);
+		after
+	}
+	
+	/// A copy of the position with the pieces moved, without updating anything but the board
+	fn with_pieces_moved(&self, mv: Move) -> Position {
+		let mut after = *self;
+		let undo = Undo {
+			captured: Piece::air(),
+			castling: self.castling,
+			en_passant: self.en_passant,
+			halfmove_clock: self.halfmove_clock,
+			fullmove_number: self.fullmove_number,
+			holdings: self.holdings,
+			promoted: self.promoted,
+			checks_given: self.checks_given,
+			exploded: [Piece::air(); 9],
+			hash: self.zobrist_hash()
+		};
+		
+		after.move_pieces(mv, &undo);
+		after
+	}
+	
+	/// Moves the pieces on the board, with `undo` holding the castling rights from before the move
+	fn move_pieces(&mut self, mv: Move, undo: &Undo) {
+		let piece = self.piece_at(mv.from);
+		let explodes = self.variant == Variant::Atomic && self.is_capture(mv);
+		
+		match mv.kind {
+			MoveKind::Normal => {
+				self.set_piece(mv.from, Piece::air());
+				self.set_piece(mv.to, piece);
+			}
+			MoveKind::Promotion(piece_type) => {
+				self.set_piece(mv.from, Piece::air());
+				self.set_piece(mv.to, Piece::new(piece_type, piece.color));
+			}
+			MoveKind::EnPassant => {
+				self.set_piece(mv.from, Piece::air());
+				self.set_piece(Square::new(mv.to.file(), mv.from.rank()), Piece::air());
+				self.set_piece(mv.to, piece);
+			}
+			MoveKind::Castle(side) => {
+				let rook = undo.castling.rook_square(piece.color, side).unwrap_or(mv.to);
+				let rook_piece = self.piece_at(rook);
+				
+				self.set_piece(mv.from, Piece::air());
+				self.set_piece(rook, Piece::air());
+				self.set_piece(mv.to, piece);
+				self.set_piece(Square::new(side.rook_file(), mv.to.rank()), rook_piece);
+			}
+			MoveKind::Drop(piece_type) => self.set_piece(mv.to, Piece::new(piece_type, self.side_to_move))
+		}
+		
+		if explodes {
+			self.explode(mv.to);
+		}
+	}
+	
+	/// Keeps the promoted pieces on their squares after the pieces have moved, and in crazyhouse
+	/// takes a dropped piece out of the hand and puts a captured piece into the capturer's hand,
+	/// as a pawn if it had been promoted
+	fn update_holdings(&mut self, mv: Move, captured: Piece, captured_square: Square, undo: &Undo) {
+		let us = self.side_to_move;
+		let captured_promoted = undo.promoted.contains(captured_square);
+		
+		self.promoted.remove(captured_square);
+		match mv.kind {
+			MoveKind::Castle(side) => {
+				if let Some(rook) = undo.castling.rook_square(us, side).filter(|&rook| undo.promoted.contains(rook)) {
+					self.promoted.remove(rook);
+					self.promoted.insert(Square::new(side.rook_file(), mv.to.rank()));
+				}
+			}
+			MoveKind::Promotion(_) if self.holdings.is_some() => {
+				self.promoted.insert(mv.to);
+			}
+			_ if undo.promoted.contains(mv.from) => {
+				self.promoted.remove(mv.from);
+				self.promoted.insert(mv.to);
+			}
+			_ => {}
+		}
+		
+		let Some(holdings) = &mut self.holdings else {
+			return;
+		};
+		
+		if let MoveKind::Drop(piece_type) = mv.kind {
+			holdings.remove(us, piece_type);
+		}
+		
+		if captured != Piece::air() {
+			holdings.add(us, if captured_promoted { PieceType::Pawn } else { captured.piece_type });
+		}
+	}
+}
+
+/// Writes the move in coordinate notation like `e2e4`, or `e7e8q` for a promotion,
+/// where castling is written as the king moving two files like `e1g1` and a drop like `N@f3`
+impl Display for Move {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		if let MoveKind::Drop(piece_type) = self.kind {
+			return write!(f, "{}@{}", Piece::new(piece_type, PieceColor::White), self.to);
+		}
+		
+		write!(f, "{}{}", self.from, self.to)?;
+		
+		if let MoveKind::Promotion(piece_type) = self.kind {
+			write!(f, "{}", Piece::new(piece_type, PieceColor::Black))?;
+		}
+		
+		Ok(())
+	}
+}
+
+impl Fen {
+	/// Every move the side to move can make without looking at whether it leaves their own king in check,
+	/// see [`Position::pseudo_legal_moves`]
+	pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+		Position::from(self).pseudo_legal_moves()
+	}
+	
+	/// Every move the side to move can make that does not leave their own king in check
+	pub fn legal_moves(&self) -> Vec<Move> {
+		Position::from(self).legal_moves()
+	}
+	
+	/// The FEN after making the move, with the side to move, castling rights,
+	/// en passant square and both clocks updated, see [`Position::make_move`]
+	pub fn play(&self, mv: Move) -> Fen {
+		Fen::from(Position::from(self).play(mv))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn make_and_unmake() {
+		let fens = [
+			"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+			"rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+			"r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1"
+		];
+		
+		for fen in fens {
+			let mut position = Position::from(Fen::parse(fen).unwrap());
+			let before = position;
+			
+			for mv in position.legal_moves() {
+				let undo = position.make_move(mv);
+				position.unmake_move(mv, undo);
+				assert_eq!(position, before, "{mv} in {fen}");
+			}
+		}
+	}
+	
+	#[test]
+	fn saturated_clocks() {
+		let mut position = Position::from(Fen::parse("4k3/8/8/8/8/8/8/4K3 b - - 4294967295 4294967295").unwrap());
+		let before = position;
+		let mv = position.legal_moves()[0];
+		let undo = position.make_move(mv);
+		
+		assert_eq!((position.halfmove_clock, position.fullmove_number), (u32::MAX, u32::MAX));
+		position.unmake_move(mv, undo);
+		assert_eq!(position, before);
+	}
+}
@@ -1,25 +1,70 @@
+use std::cmp::Ordering;
+use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::ops::{Index, IndexMut};
+use std::str::FromStr;
 
+use crate::bitboard::Bitboard;
+use crate::crazyhouse::Holdings;
+use crate::moves::CastlingSide;
+use crate::square::{File, Rank, Square};
+use crate::variant::Variant;
+
+/// A chess position as described by FEN notation.
+/// 
+/// Ranks are always indexed from White's side of the board, so index 0 is rank 1 and index 7 is rank 8,
+/// while FEN notation lists them the other way around, starting with rank 8
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Fen {
-	/// A vector of information for every row starting at index 0 up to index 7,
+	/// Information for every row starting at index 0 up to index 7,
 	/// where the index maps to chessboard rows 1-8 starting at row 1 for index 0
-	pub rows: Vec<Row>
+	pub rows: [Row; 8],
+	/// The side that plays the next move, [`PieceColor::White`] if the FEN notation leaves it out
+	pub side_to_move: PieceColor,
+	/// Which castling moves are still available, none if the FEN notation leaves it out
+	pub castling: CastlingRights,
+	/// The square a pawn skipped over with a double step on the last move, if any
+	pub en_passant: Option<Square>,
+	/// The number of halfmoves since the last capture or pawn move, 0 if the FEN notation leaves it out
+	pub halfmove_clock: u32,
+	/// The number of the current move, starting at 1 and going up after every Black move,
+	/// 1 if the FEN notation leaves it out
+	pub fullmove_number: u32,
+	/// The pieces in hand for crazyhouse and bughouse, `None` for variants without drops
+	pub holdings: Option<Holdings>,
+	/// The squares of pieces that were promoted from pawns, marked with a `~` after the piece,
+	/// which go back to the hand as pawns when captured in crazyhouse
+	pub promoted: Bitboard,
+	/// The rules the position is played by, which FEN notation does not record and comes from [`ParseOptions`]
+	pub variant: Variant,
+	/// The number of checks White gave at index 0, and Black at index 1, which only Three-check counts
+	pub checks_given: [u8; 2]
+}
+
+/// The castling moves that are still available to both sides, each stored as the file of the rook
+/// that castles, which is file H or A in standard chess and can be any file in Chess960
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct CastlingRights {
+	pub white_king_side: Option<File>,
+	pub white_queen_side: Option<File>,
+	pub black_king_side: Option<File>,
+	pub black_queen_side: Option<File>
 }
 
-#[derive(Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Row {
-	/// A vector of information for every piece starting at index 0 up to index 7,
+	/// Information for every piece starting at index 0 up to index 7,
 	/// where the index maps to chessboard columns A-H starting at column A for index 0
-	pub pieces: Vec<Piece>
+	pub pieces: [Piece; 8]
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Piece {
 	pub piece_type: PieceType,
 	pub color: PieceColor
 }
 
-#[derive(Default, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum PieceType {
 	Pawn,
 	Rook,
@@ -31,7 +76,7 @@ pub enum PieceType {
 	Empty
 }
 
-#[derive(Default, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum PieceColor {
 	White,
 	Black,
@@ -39,149 +84,541 @@ pub enum PieceColor {
 	Empty
 }
 
+/// Options that change how [`Fen::parse_with`] reads FEN notation
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ParseOptions {
+	/// Reads lowercase piece letters as White pieces and uppercase ones as Black pieces,
+	/// the reverse of the FEN standard that older versions of this crate used
+	pub legacy_piece_case: bool,
+	/// The rules the position is played by, which decides whether three-check counters are read
+	pub variant: Variant
+}
+
+/// The reason a FEN notation string could not be parsed.
+/// 
+/// Every `offset` is a byte offset into the input string,
+/// every `rank` is a rank index where 0 is rank 1 and 7 is rank 8,
+/// and every `file` is a file index where 0 is file A and 7 is file H
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum FenError {
+	/// A character that is neither a piece nor a count of empty squares,
+	/// or a `~` that does not follow a piece
+	UnknownCharacter {
+		offset: usize,
+		rank: usize,
+		file: usize,
+		character: char
+	},
+	/// A character that describes squares past file H
+	RankTooLong {
+		offset: usize,
+		rank: usize,
+		file: usize,
+		character: char
+	},
+	/// A rank that ends before file H, where `file` is the first square not described
+	RankTooShort {
+		offset: usize,
+		rank: usize,
+		file: usize
+	},
+	/// A `/` that starts a ninth rank
+	TooManyRanks {
+		offset: usize
+	},
+	/// An input that ends after `count` ranks
+	TooFewRanks {
+		offset: usize,
+		count: usize
+	},
+	/// A side to move field that is neither `w` nor `b`
+	InvalidSideToMove {
+		offset: usize
+	},
+	/// A castling field character that is not one of `KQkq`, appears twice, or follows a `-`
+	InvalidCastling {
+		offset: usize,
+		character: char
+	},
+	/// An en passant field that is neither `-` nor a square like `e3`
+	InvalidEnPassant {
+		offset: usize
+	},
+	/// A halfmove clock field that is not a number
+	InvalidHalfmoveClock {
+		offset: usize
+	},
+	/// A fullmove number field that is not a number
+	InvalidFullmoveNumber {
+		offset: usize
+	},
+	/// A seventh whitespace separated field, not counting three-check counters
+	TooManyFields {
+		offset: usize
+	},
+	/// A character in the crazyhouse holdings that is not a piece which can be dropped
+	InvalidHoldings {
+		offset: usize,
+		character: char
+	},
+	/// A three-check field that is neither the checks given like `+2+1` nor the checks remaining like `1+2`
+	InvalidCheckCount {
+		offset: usize
+	}
+}
+
 impl Fen {
 	/// Takes a FEN notation string and converts it to a [`Fen`].
 	/// 
-	/// The rows in the FEN notation are separated by a `/`,
-	/// and as of right now king and queen status is not handled,
-	/// and neither is move count and side playing  
-	#[allow(clippy::too_many_lines)]
+	/// # Panics
+	/// 
+	/// Panics if the input is not valid FEN notation,
+	/// use [`Fen::parse`] for untrusted input
 	pub fn from_string(input: &str) -> Self {
-		println!("Parsing input: {input}");
+		Fen::parse(input).unwrap_or_else(|error| panic!("Invalid FEN notation: {error}"))
+	}
+	
+	/// Takes a FEN notation string and converts it to a [`Fen`],
+	/// returning a [`FenError`] describing the first problem found instead of panicking.
+	/// 
+	/// The six fields are separated by whitespace, and any fields after the piece placement
+	/// can be left out, in which case White is to move, no castling or en passant is available,
+	/// the halfmove clock is 0 and the fullmove number is 1.
+	/// 
+	/// The rows in the piece placement are separated by a `/`,
+	/// every row has to describe exactly 8 squares and there have to be exactly 8 rows.
+	/// 
+	/// Crazyhouse holdings can follow the rows either in brackets like `[QRp]` or as a ninth row like `/QRp`,
+	/// and a `~` after a piece marks it as promoted.
+	/// A FEN with holdings is read as [`Variant::Crazyhouse`]
+	pub fn parse(input: &str) -> Result<Self, FenError> {
+		Fen::parse_with(input, ParseOptions::default())
+	}
+	
+	/// Same as [`Fen::parse`], but reads the input according to the given [`ParseOptions`].
+	/// 
+	/// For [`Variant::ThreeCheck`], the checks remaining like `3+3` can come after the en passant field,
+	/// and the checks given like `+0+0` can come after the fullmove number
+	pub fn parse_with(input: &str, options: ParseOptions) -> Result<Self, FenError> {
+		let mut fen = Fen {
+			rows: [Row::empty(); 8],
+			side_to_move: PieceColor::White,
+			castling: CastlingRights::none(),
+			en_passant: None,
+			halfmove_clock: 0,
+			fullmove_number: 1,
+			holdings: None,
+			promoted: Bitboard::EMPTY,
+			variant: options.variant,
+			checks_given: [0; 2]
+		};
 		
-		let mut rows = Vec::<Row>::with_capacity(8);
+		let mut fields = input
+			.split_ascii_whitespace()
+			.map(|field| (field.as_ptr() as usize - input.as_ptr() as usize, field))
+			.peekable();
 		
-		for input_row in input.split('/') {
-			let mut row = Row::empty();
-			let mut last_index: Option<usize> = None;
-			
-			for (i, char) in input_row.chars().enumerate() {
-				assert!(i <= 7, "More than 8 characters provided in input row {input_row}");
+		let (placement_offset, placement) = fields.next().unwrap_or((input.len(), ""));
+		let (placement, holdings) = split_holdings(placement);
+		
+		if let Some(holdings) = holdings {
+			let holdings_offset = placement_offset + (holdings.as_ptr() as usize - placement.as_ptr() as usize);
+			fen.holdings = Some(Holdings::parse(holdings, holdings_offset, options)?);
+		}
+		
+		match (fen.variant, fen.holdings) {
+			(Variant::Standard, Some(_)) => fen.variant = Variant::Crazyhouse,
+			(Variant::Crazyhouse, None) => fen.holdings = Some(Holdings::new()),
+			_ => {}
+		}
+		
+		(fen.rows, fen.promoted) = parse_placement(placement, placement_offset, options)?;
+		
+		if let Some((offset, field)) = fields.next() {
+			fen.side_to_move = match field {
+				"w" => PieceColor::White,
+				"b" => PieceColor::Black,
+				_ => return Err(FenError::InvalidSideToMove {
+					offset
+				})
+			};
+		}
+		
+		if let Some((offset, field)) = fields.next() {
+			fen.castling = CastlingRights::parse(field, offset, &fen.rows)?;
+		}
+		
+		if let Some((offset, field)) = fields.next() {
+			fen.en_passant = match field {
+				"-" => None,
+				_ => Some(Square::from_algebraic(field).ok_or(FenError::InvalidEnPassant {
+					offset
+				})?)
+			};
+		}
+		
+		let three_check = fen.variant == Variant::ThreeCheck;
+		
+		if let Some((offset, field)) = fields.next_if(|(_, field)| three_check && field.contains('+') && !field.starts_with('+')) {
+			let remaining = parse_check_counts(field).ok_or(FenError::InvalidCheckCount {
+				offset
+			})?;
+			fen.checks_given = remaining.map(|count| 3 - count);
+		}
+		
+		if let Some((offset, field)) = fields.next() {
+			fen.halfmove_clock = field.parse().map_err(|_| FenError::InvalidHalfmoveClock {
+				offset
+			})?;
+		}
+		
+		if let Some((offset, field)) = fields.next() {
+			fen.fullmove_number = field.parse().map_err(|_| FenError::InvalidFullmoveNumber {
+				offset
+			})?;
+		}
+		
+		if let Some((offset, field)) = fields.next_if(|(_, field)| three_check && field.starts_with('+')) {
+			fen.checks_given = parse_check_counts(&field[1..]).ok_or(FenError::InvalidCheckCount {
+				offset
+			})?;
+		}
+		
+		if let Some((offset, _)) = fields.next() {
+			return Err(FenError::TooManyFields {
+				offset
+			});
+		}
+		
+		Ok(fen)
+	}
+	
+	/// Writes the position in Shredder-FEN, where the castling field always names the files of the castling rooks like `HAha`.
+	/// 
+	/// The [`Display`] implementation writes X-FEN instead, which is the same as standard FEN for standard chess
+	pub fn to_shredder_fen(&self) -> String {
+		let fen = self.to_string();
+		let mut fields: Vec<&str> = fen.split(' ').collect();
+		let castling = self.castling_field(true);
+		fields[2] = &castling;
+		fields.join(" ")
+	}
+	
+	/// The castling field as X-FEN writes it, with `KQkq` for the outermost rook on each side of the king
+	/// and the file of the rook otherwise, or as Shredder-FEN writes it, always with the file
+	fn castling_field(&self, shredder: bool) -> String {
+		let mut field = String::new();
+		
+		for color in [PieceColor::White, PieceColor::Black] {
+			for side in [CastlingSide::KingSide, CastlingSide::QueenSide] {
+				let Some(file) = self.castling.rook_file(color, side) else {
+					continue;
+				};
 				
-				let index: usize = if last_index.is_none() {
-					i
+				let character = if !shredder && outermost_rook(&self.rows, color, side).is_none_or(|outermost| outermost == file) {
+					match side {
+						CastlingSide::KingSide => 'k',
+						CastlingSide::QueenSide => 'q'
+					}
 				} else {
-					#[allow(clippy::unnecessary_unwrap)]
-					last_index.unwrap()
+					file.to_char()
 				};
 				
-				match char {
-					'p' => {
-						row.pieces[index] = Piece::white_piece(PieceType::Pawn);
-						last_index = Some(index + 1);
-					},
-					'P' => {
-						row.pieces[index] = Piece::black_piece(PieceType::Pawn);
-						last_index = Some(index + 1);
-					},
-					'r' => {
-						row.pieces[index] = Piece::white_piece(PieceType::Rook);
-						last_index = Some(index + 1);
-					},
-					'R' => {
-						row.pieces[index] = Piece::black_piece(PieceType::Rook);
-						last_index = Some(index + 1);
-					},
-					'n' => {
-						row.pieces[index] = Piece::white_piece(PieceType::Knight);
-						last_index = Some(index + 1);
-					},
-					'N' => {
-						row.pieces[index] = Piece::black_piece(PieceType::Knight);
-						last_index = Some(index + 1);
-					},
-					'b' => {
-						row.pieces[index] = Piece::white_piece(PieceType::Bishop);
-						last_index = Some(index + 1);
-					},
-					'B' => {
-						row.pieces[index] = Piece::black_piece(PieceType::Bishop);
-						last_index = Some(index + 1);
-					},
-					'q' => {
-						row.pieces[index] = Piece::white_piece(PieceType::Queen);
-						last_index = Some(index + 1);
-					},
-					'Q' => {
-						row.pieces[index] = Piece::black_piece(PieceType::Queen);
-						last_index = Some(index + 1);
-					},
-					'k' => {
-						row.pieces[index] = Piece::white_piece(PieceType::King);
-						last_index = Some(index + 1);
-					},
-					'K' => {
-						row.pieces[index] = Piece::black_piece(PieceType::King);
-						last_index = Some(index + 1);
-					},
-					'1' => {
-						row.pieces[index] = Piece::air();
-						last_index = Some(index + 1);
-					},
-					'2' => {
-						for offset in 0..2 {
-							row.pieces[index + offset] = Piece::air();
-						}
-						last_index = Some(index + 2);
-					}
-					'3' => {
-						for offset in 0..3 {
-							row.pieces[index + offset] = Piece::air();
-						}
-						last_index = Some(index + 3);
-					}
-					'4' => {
-						for offset in 0..4 {
-							row.pieces[index + offset] = Piece::air();
-						}
-						last_index = Some(index + 4);
-					}
-					'5' => {
-						for offset in 0..5 {
-							row.pieces[index + offset] = Piece::air();
-						}
-						last_index = Some(index + 5);
-					}
-					'6' => {
-						for offset in 0..6 {
-							row.pieces[index + offset] = Piece::air();
-						}
-						last_index = Some(index + 6);
-					}
-					'7' => {
-						for offset in 0..7 {
-							row.pieces[index + offset] = Piece::air();
-						}
-						last_index = Some(index + 7);
-					}
-					'8' => {
-						for offset in 0..8 {
-							row.pieces[index + offset] = Piece::air();
+				field.push(if color == PieceColor::White { character.to_ascii_uppercase() } else { character });
+			}
+		}
+		
+		if field.is_empty() {
+			field.push('-');
+		}
+		
+		field
+	}
+	
+	/// The row of pieces on the given rank, from file A to file H
+	pub fn row(&self, rank: Rank) -> &Row {
+		&self.rows[rank.index()]
+	}
+	
+	/// The piece on the given square, which is [`Piece::air`] for an empty square
+	pub fn get(&self, square: Square) -> Piece {
+		self[square]
+	}
+	
+	/// Puts a piece on the given square, replacing whatever was there,
+	/// and [`Piece::air`] empties the square
+	pub fn set(&mut self, square: Square, piece: Piece) {
+		self[square] = piece;
+	}
+}
+
+/// Parses two three-check counters like `2+1`, White's first, each at most 3
+fn parse_check_counts(field: &str) -> Option<[u8; 2]> {
+	let (white, black) = field.split_once('+')?;
+	let counts = [white.parse().ok()?, black.parse().ok()?];
+	
+	counts.iter().all(|&count| count <= 3).then_some(counts)
+}
+
+/// Splits crazyhouse holdings off the piece placement field, either in brackets at the end
+/// or as a ninth row without any digits, and returns the rows and the holdings without brackets
+fn split_holdings(placement: &str) -> (&str, Option<&str>) {
+	if let Some(inside) = placement.strip_suffix(']') {
+		if let Some(open) = inside.rfind('[') {
+			return (&placement[..open], Some(&inside[open + 1..]));
+		}
+	}
+	
+	match placement.match_indices('/').nth(7) {
+		Some((slash, _)) if !placement[slash + 1..].contains(|character: char| character.is_ascii_digit() || character == '/') => {
+			(&placement[..slash], Some(&placement[slash + 1..]))
+		}
+		_ => (placement, None)
+	}
+}
+
+/// Parses the piece placement field of a FEN notation string into rows indexed from rank 1
+/// and the squares of pieces marked as promoted,
+/// where `field_offset` is the byte offset of the field in the whole input
+fn parse_placement(placement: &str, field_offset: usize, options: ParseOptions) -> Result<([Row; 8], Bitboard), FenError> {
+	let mut rows = [Row::empty(); 8];
+	let mut promoted = Bitboard::EMPTY;
+	let mut row_offset = field_offset;
+	let mut row_count = 0;
+	
+	for (row_number, input_row) in placement.split('/').enumerate() {
+		if row_number > 7 {
+			return Err(FenError::TooManyRanks {
+				offset: row_offset - 1
+			});
+		}
+		
+		let rank = 7 - row_number;
+		let mut row = Row::empty();
+		let mut file = 0;
+		let mut after_piece = false;
+		
+		for (i, character) in input_row.char_indices() {
+			let offset = row_offset + i;
+			
+			if character == '~' && after_piece {
+				promoted.insert(Square::new(File::ALL[file - 1], Rank::ALL[rank]));
+				after_piece = false;
+				continue;
+			}
+			
+			let (piece, width) = match character {
+				'1'..='8' => (Piece::air(), character as usize - '0' as usize),
+				_ => match Piece::from_char(character) {
+					Some(mut piece) => {
+						if options.legacy_piece_case {
+							piece.color = piece.color.opposite();
 						}
-						last_index = Some(index + 8);
-					}
-					_ => unreachable!("Unknown values in input FEN notation!")
+						(piece, 1)
+					},
+					None => return Err(FenError::UnknownCharacter {
+						offset,
+						rank,
+						file,
+						character
+					})
 				}
+			};
+			
+			if file + width > 8 {
+				return Err(FenError::RankTooLong {
+					offset,
+					rank,
+					file,
+					character
+				});
 			}
 			
-			rows.push(row);
+			for square in &mut row.pieces[file..file + width] {
+				*square = piece;
+			}
+			file += width;
+			after_piece = piece != Piece::air();
 		}
 		
-		Fen {
-			rows
+		if file < 8 {
+			return Err(FenError::RankTooShort {
+				offset: row_offset + input_row.len(),
+				rank,
+				file
+			});
+		}
+		
+		row_offset += input_row.len() + 1;
+		rows[rank] = row;
+		row_count += 1;
+	}
+	
+	if row_count < 8 {
+		return Err(FenError::TooFewRanks {
+			offset: field_offset + placement.len(),
+			count: row_count
+		});
+	}
+	
+	Ok((rows, promoted))
+}
+
+impl CastlingRights {
+	pub fn none() -> Self {
+		CastlingRights {
+			white_king_side: None,
+			white_queen_side: None,
+			black_king_side: None,
+			black_queen_side: None
+		}
+	}
+	
+	/// Every castling right of standard chess, with the rooks on files H and A
+	pub fn all() -> Self {
+		CastlingRights {
+			white_king_side: Some(File::H),
+			white_queen_side: Some(File::A),
+			black_king_side: Some(File::H),
+			black_queen_side: Some(File::A)
+		}
+	}
+	
+	/// Whether the given color can still castle to the given side
+	pub fn get(&self, color: PieceColor, side: CastlingSide) -> bool {
+		self.rook_file(color, side).is_some()
+	}
+	
+	/// The file of the rook that castles with the king on the given side for the given color,
+	/// if that castling right is still available
+	pub fn rook_file(&self, color: PieceColor, side: CastlingSide) -> Option<File> {
+		match (color, side) {
+			(PieceColor::White, CastlingSide::KingSide) => self.white_king_side,
+			(PieceColor::White, CastlingSide::QueenSide) => self.white_queen_side,
+			(PieceColor::Black, CastlingSide::KingSide) => self.black_king_side,
+			(PieceColor::Black, CastlingSide::QueenSide) => self.black_queen_side,
+			(PieceColor::Empty, _) => None
+		}
+	}
+	
+	/// The square of the rook that castles with the king on the given side for the given color,
+	/// if that castling right is still available
+	pub fn rook_square(&self, color: PieceColor, side: CastlingSide) -> Option<Square> {
+		Some(Square::new(self.rook_file(color, side)?, home_rank(color)?))
+	}
+	
+	/// Gives the given color the right to castle to the given side with the rook on the given file,
+	/// or takes the right away with `None`
+	pub fn set(&mut self, color: PieceColor, side: CastlingSide, rook_file: Option<File>) {
+		match (color, side) {
+			(PieceColor::White, CastlingSide::KingSide) => self.white_king_side = rook_file,
+			(PieceColor::White, CastlingSide::QueenSide) => self.white_queen_side = rook_file,
+			(PieceColor::Black, CastlingSide::KingSide) => self.black_king_side = rook_file,
+			(PieceColor::Black, CastlingSide::QueenSide) => self.black_queen_side = rook_file,
+			(PieceColor::Empty, _) => {}
 		}
 	}
+	
+	/// Parses the castling field of a FEN notation string, where `offset` is the byte offset of the field
+	/// in the whole input and `rows` is the piece placement the rights belong to.
+	/// 
+	/// The field is either `-` or a mix of `KQkq`, standing for the outermost rook on each side of the king like in X-FEN,
+	/// and the files of the castling rooks like `HAha` in Shredder-FEN, uppercase for White and lowercase for Black
+	fn parse(field: &str, offset: usize, rows: &[Row; 8]) -> Result<Self, FenError> {
+		let mut castling = CastlingRights::none();
+		
+		if field == "-" {
+			return Ok(castling);
+		}
+		
+		for (i, character) in field.char_indices() {
+			let error = FenError::InvalidCastling {
+				offset: offset + i,
+				character
+			};
+			let color = if character.is_ascii_uppercase() { PieceColor::White } else { PieceColor::Black };
+			
+			let (side, file) = match character.to_ascii_lowercase() {
+				'k' => (CastlingSide::KingSide, outermost_rook(rows, color, CastlingSide::KingSide).unwrap_or(File::H)),
+				'q' => (CastlingSide::QueenSide, outermost_rook(rows, color, CastlingSide::QueenSide).unwrap_or(File::A)),
+				letter => {
+					let file = File::from_char(letter).ok_or(error)?;
+					let king_file = home_king_file(rows, color).unwrap_or(File::E);
+					
+					match file.cmp(&king_file) {
+						Ordering::Greater => (CastlingSide::KingSide, file),
+						Ordering::Less => (CastlingSide::QueenSide, file),
+						Ordering::Equal => return Err(error)
+					}
+				}
+			};
+			
+			if castling.get(color, side) {
+				return Err(error);
+			}
+			castling.set(color, side, Some(file));
+		}
+		
+		Ok(castling)
+	}
+}
+
+/// The rank a color's king and rooks start on
+fn home_rank(color: PieceColor) -> Option<Rank> {
+	match color {
+		PieceColor::White => Some(Rank::One),
+		PieceColor::Black => Some(Rank::Eight),
+		PieceColor::Empty => None
+	}
+}
+
+/// The file of the color's king if it stands on its home rank
+fn home_king_file(rows: &[Row; 8], color: PieceColor) -> Option<File> {
+	let row = &rows[home_rank(color)?.index()];
+	let file = row.pieces.iter().position(|&piece| piece == Piece::new(PieceType::King, color))?;
+	File::from_index(file)
+}
+
+/// The file of the color's rook on its home rank that is furthest from the king on the given side,
+/// which is the rook `K` or `Q` stands for in X-FEN
+fn outermost_rook(rows: &[Row; 8], color: PieceColor, side: CastlingSide) -> Option<File> {
+	let row = &rows[home_rank(color)?.index()];
+	let king_file = home_king_file(rows, color);
+	let is_rook = |file: &File| row.pieces[file.index()] == Piece::new(PieceType::Rook, color);
+	
+	match side {
+		CastlingSide::KingSide => File::ALL.into_iter().rev().take_while(|&file| Some(file) != king_file).find(is_rook),
+		CastlingSide::QueenSide => File::ALL.into_iter().take_while(|&file| Some(file) != king_file).find(is_rook)
+	}
 }
 
 impl Row {
 	pub fn empty() -> Self {
 		Row {
-			pieces: vec![Piece::air(); 8]
+			pieces: [Piece::air(); 8]
+		}
+	}
+}
+
+impl PieceColor {
+	/// The other side, or [`PieceColor::Empty`] for [`PieceColor::Empty`]
+	pub fn opposite(self) -> Self {
+		match self {
+			PieceColor::White => PieceColor::Black,
+			PieceColor::Black => PieceColor::White,
+			PieceColor::Empty => PieceColor::Empty
 		}
 	}
 }
 
 impl Piece {
+	pub fn new(piece_type: PieceType, color: PieceColor) -> Self {
+		Piece {
+			piece_type,
+			color
+		}
+	}
+	
 	pub fn air() -> Self {
 		Piece {
 			piece_type: PieceType::Empty,
@@ -189,13 +626,35 @@ impl Piece {
 		}
 	}
 	
+	/// Converts a FEN piece letter to a [`Piece`], where uppercase letters are White pieces,
+	/// returning `None` for anything that is not a piece letter
+	pub fn from_char(character: char) -> Option<Self> {
+		let piece = match character {
+			'P' => Piece::white_piece(PieceType::Pawn),
+			'p' => Piece::black_piece(PieceType::Pawn),
+			'R' => Piece::white_piece(PieceType::Rook),
+			'r' => Piece::black_piece(PieceType::Rook),
+			'N' => Piece::white_piece(PieceType::Knight),
+			'n' => Piece::black_piece(PieceType::Knight),
+			'B' => Piece::white_piece(PieceType::Bishop),
+			'b' => Piece::black_piece(PieceType::Bishop),
+			'Q' => Piece::white_piece(PieceType::Queen),
+			'q' => Piece::black_piece(PieceType::Queen),
+			'K' => Piece::white_piece(PieceType::King),
+			'k' => Piece::black_piece(PieceType::King),
+			_ => return None
+		};
+		
+		Some(piece)
+	}
+	
 	pub fn white_piece(piece_type: PieceType) -> Self {
 		Piece {
 			piece_type,
 			color: PieceColor::White
 		}
 	}
-
+	
 	pub fn black_piece(piece_type: PieceType) -> Self {
 		Piece {
 			piece_type,
@@ -207,10 +666,10 @@ impl Piece {
 impl Default for Fen {
 	/// The starting position for a chess game
 	fn default() -> Self {
-		let pawn_row_white = vec![Piece::white_piece(PieceType::Pawn); 8];
-		let pawn_row_black = vec![Piece::black_piece(PieceType::Pawn); 8];
+		let pawn_row_white = [Piece::white_piece(PieceType::Pawn); 8];
+		let pawn_row_black = [Piece::black_piece(PieceType::Pawn); 8];
 		let king_row_white =
-			vec![
+			[
 				Piece::white_piece(PieceType::Rook),
 				Piece::white_piece(PieceType::Knight),
 				Piece::white_piece(PieceType::Bishop),
@@ -221,7 +680,7 @@ impl Default for Fen {
 				Piece::white_piece(PieceType::Rook),
 			];
 		let king_row_black =
-			vec![
+			[
 				Piece::black_piece(PieceType::Rook),
 				Piece::black_piece(PieceType::Knight),
 				Piece::black_piece(PieceType::Bishop),
@@ -249,10 +708,19 @@ impl Default for Fen {
 			pieces: king_row_black
 		};
 		
-		let rows = vec![row_1, row_2, row_3, row_4, row_5, row_6, row_7, row_8];
+		let rows = [row_1, row_2, row_3, row_4, row_5, row_6, row_7, row_8];
 		
 		Fen {
-			rows
+			rows,
+			side_to_move: PieceColor::White,
+			castling: CastlingRights::all(),
+			en_passant: None,
+			halfmove_clock: 0,
+			fullmove_number: 1,
+			holdings: None,
+			promoted: Bitboard::EMPTY,
+			variant: Variant::Standard,
+			checks_given: [0; 2]
 		}
 	}
 }
@@ -269,18 +737,145 @@ impl Default for Piece {
 	}
 }
 
+impl Index<Square> for Fen {
+	type Output = Piece;
+	
+	fn index(&self, square: Square) -> &Self::Output {
+		&self.rows[square.rank().index()].pieces[square.file().index()]
+	}
+}
+
+impl IndexMut<Square> for Fen {
+	fn index_mut(&mut self, square: Square) -> &mut Self::Output {
+		&mut self.rows[square.rank().index()].pieces[square.file().index()]
+	}
+}
+
+impl FromStr for Fen {
+	type Err = FenError;
+	
+	fn from_str(input: &str) -> Result<Self, Self::Err> {
+		Fen::parse(input)
+	}
+}
+
+impl TryFrom<&str> for Fen {
+	type Error = FenError;
+	
+	fn try_from(input: &str) -> Result<Self, Self::Error> {
+		Fen::parse(input)
+	}
+}
+
+impl Display for FenError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match *self {
+			FenError::UnknownCharacter { offset, rank, file, character } => {
+				write!(f, "unknown character '{character}' at byte {offset} ({})", square_name(rank, file))
+			}
+			FenError::RankTooLong { offset, rank, character, .. } => {
+				write!(f, "character '{character}' at byte {offset} goes past file H of rank {}", rank + 1)
+			}
+			FenError::RankTooShort { offset, rank, file } => {
+				write!(f, "rank {} ends at byte {offset} before describing {}", rank + 1, square_name(rank, file))
+			}
+			FenError::TooManyRanks { offset } => {
+				write!(f, "'/' at byte {offset} starts a ninth rank")
+			}
+			FenError::TooFewRanks { offset, count } => {
+				write!(f, "input ends at byte {offset} after only {count} ranks")
+			}
+			FenError::InvalidSideToMove { offset } => {
+				write!(f, "side to move at byte {offset} is neither 'w' nor 'b'")
+			}
+			FenError::InvalidCastling { offset, character } => {
+				write!(f, "unexpected castling character '{character}' at byte {offset}")
+			}
+			FenError::InvalidEnPassant { offset } => {
+				write!(f, "en passant target at byte {offset} is neither '-' nor a square")
+			}
+			FenError::InvalidHalfmoveClock { offset } => {
+				write!(f, "halfmove clock at byte {offset} is not a number")
+			}
+			FenError::InvalidFullmoveNumber { offset } => {
+				write!(f, "fullmove number at byte {offset} is not a number")
+			}
+			FenError::TooManyFields { offset } => {
+				write!(f, "unexpected seventh field at byte {offset}")
+			}
+			FenError::InvalidHoldings { offset, character } => {
+				write!(f, "character '{character}' at byte {offset} is not a piece that can be held")
+			}
+			FenError::InvalidCheckCount { offset } => {
+				write!(f, "three-check counters at byte {offset} are neither like '+2+1' nor like '1+2'")
+			}
+		}
+	}
+}
+
+impl Error for FenError {}
+
+/// Formats a rank and file index as a square name like `e4`, for error messages
+fn square_name(rank: usize, file: usize) -> String {
+	format!("{}{}", char::from(b'a' + file as u8), rank + 1)
+}
+
 impl Display for Fen {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
 		let mut output_string= String::new();
 		
-		for (i, row) in self.rows.iter().enumerate() {
-			output_string.push_str(&row.to_string());
+		for (i, row) in self.rows.iter().rev().enumerate() {
+			let rank = 7 - i;
+			let mut empty = 0;
+			
+			for (file, piece) in row.pieces.iter().enumerate() {
+				if *piece == Piece::air() {
+					empty += 1;
+					continue;
+				}
+				
+				if empty > 0 {
+					output_string.push_str(&empty.to_string());
+					empty = 0;
+				}
+				
+				output_string.push_str(&piece.to_string());
+				if self.promoted.contains(Square::new(File::ALL[file], Rank::ALL[rank])) {
+					output_string.push('~');
+				}
+			}
+			
+			if empty > 0 {
+				output_string.push_str(&empty.to_string());
+			}
 			if i < 7 {
 				output_string.push('/');
 			}
 		}
 		
-		write!(f, "{output_string}")
+		if let Some(holdings) = self.holdings {
+			output_string.push_str(&format!("[{holdings}]"));
+		}
+		
+		let side_to_move = if self.side_to_move == PieceColor::Black { 'b' } else { 'w' };
+		let en_passant = match self.en_passant {
+			Some(square) => square.to_string(),
+			None => String::from("-")
+		};
+		
+		write!(
+			f,
+			"{output_string} {side_to_move} {} {en_passant} {} {}",
+			self.castling_field(false),
+			self.halfmove_clock,
+			self.fullmove_number
+		)?;
+		
+		if self.variant == Variant::ThreeCheck {
+			write!(f, " +{}+{}", self.checks_given[0], self.checks_given[1])?;
+		}
+		
+		Ok(())
 	}
 }
 
@@ -288,7 +883,7 @@ impl Display for Row {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
 		let pieces = self.pieces.iter();
 		let mut output_string= String::new();
-
+		
 		for piece in pieces {
 			output_string.push_str(&piece.to_string());
 		}
@@ -302,7 +897,7 @@ impl Display for Row {
 			.replace("___", "3")
 			.replace("__", "2")
 			.replace('_', "1");
-
+		
 		write!(f, "{output_string}")
 	}
 }
@@ -311,42 +906,42 @@ impl Display for Piece {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
 		match self.piece_type {
 			PieceType::Pawn => {
-				if self.color == PieceColor::White {
+				if self.color == PieceColor::Black {
 					write!(f, "p")
 				} else {
 					write!(f, "P")
 				}
 			}
 			PieceType::Rook => {
-				if self.color == PieceColor::White {
+				if self.color == PieceColor::Black {
 					write!(f, "r")
 				} else {
 					write!(f, "R")
 				}
 			}
 			PieceType::Knight => {
-				if self.color == PieceColor::White {
+				if self.color == PieceColor::Black {
 					write!(f, "n")
 				} else {
 					write!(f, "N")
 				}
 			}
 			PieceType::Bishop => {
-				if self.color == PieceColor::White {
+				if self.color == PieceColor::Black {
 					write!(f, "b")
 				} else {
 					write!(f, "B")
 				}
 			}
 			PieceType::Queen => {
-				if self.color == PieceColor::White {
+				if self.color == PieceColor::Black {
 					write!(f, "q")
 				} else {
 					write!(f, "Q")
 				}
 			}
 			PieceType::King => {
-				if self.color == PieceColor::White {
+				if self.color == PieceColor::Black {
 					write!(f, "k")
 				} else {
 					write!(f, "K")
@@ -357,4 +952,66 @@ impl Display for Piece {
 			}
 		}
 	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn short_rank() {
+		assert_eq!(Fen::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN").err(), Some(FenError::RankTooShort {
+			offset: 42,
+			rank: 0,
+			file: 7
+		}));
+	}
+	
+	#[test]
+	fn long_rank() {
+		assert_eq!(Fen::parse("7pp/8/8/8/8/8/8/8").err(), Some(FenError::RankTooLong {
+			offset: 2,
+			rank: 7,
+			file: 8,
+			character: 'p'
+		}));
+	}
+	
+	#[test]
+	fn unknown_piece() {
+		assert_eq!(Fen::parse("rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR").err(), Some(FenError::UnknownCharacter {
+			offset: 13,
+			rank: 6,
+			file: 4,
+			character: 'x'
+		}));
+	}
+	
+	#[test]
+	fn round_trip() {
+		let inputs = [
+			"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+			"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+			"rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+			"8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 40"
+		];
+		
+		for input in inputs {
+			let fen = Fen::parse(input).unwrap();
+			
+			assert_eq!(fen.to_string(), input);
+			assert_eq!(Fen::parse(&fen.to_string()), Ok(fen));
+		}
+	}
+	
+	#[test]
+	fn castling_field() {
+		let inner = Fen::parse("4k3/8/8/8/8/8/8/R3K1RR w GA - 0 1").unwrap();
+		let outer = Fen::parse("4k3/8/8/8/8/8/8/R3K1RR w HA - 0 1").unwrap();
+		
+		assert_eq!(inner.to_string(), "4k3/8/8/8/8/8/8/R3K1RR w GQ - 0 1");
+		assert_eq!(outer.to_string(), "4k3/8/8/8/8/8/8/R3K1RR w KQ - 0 1");
+		assert_eq!(inner.to_shredder_fen(), "4k3/8/8/8/8/8/8/R3K1RR w GA - 0 1");
+		assert_eq!(Fen::parse(&inner.to_string()), Ok(inner));
+	}
 }
\ No newline at end of file
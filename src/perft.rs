@@ -0,0 +1,250 @@
+use std::fmt::{Display, Formatter};
+
+use crate::moves::Move;
+use crate::parser::Fen;
+use crate::position::Position;
+
+/// A well-known position with its published perft counts, for checking the move generator
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ReferencePosition {
+	pub name: &'static str,
+	pub fen: &'static str,
+	/// The number of leaf nodes at every depth, starting at depth 1 for index 0
+	pub nodes: &'static [u64]
+}
+
+/// A depth where [`perft`] disagrees with the published count of a [`ReferencePosition`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PerftMismatch {
+	pub name: &'static str,
+	pub depth: u32,
+	pub expected: u64,
+	pub found: u64
+}
+
+/// The standard perft positions from the Chess Programming Wiki
+pub const REFERENCE_POSITIONS: [ReferencePosition; 6] = [
+	ReferencePosition {
+		name: "Start position",
+		fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+		nodes: &[20, 400, 8_902, 197_281, 4_865_609, 119_060_324]
+	},
+	ReferencePosition {
+		name: "Kiwipete",
+		fen: "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+		nodes: &[48, 2_039, 97_862, 4_085_603, 193_690_690]
+	},
+	ReferencePosition {
+		name: "Position 3",
+		fen: "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+		nodes: &[14, 191, 2_812, 43_238, 674_624, 11_030_083, 178_633_661]
+	},
+	ReferencePosition {
+		name: "Position 4",
+		fen: "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+		nodes: &[6, 264, 9_467, 422_333, 15_833_292, 706_045_033]
+	},
+	ReferencePosition {
+		name: "Position 5",
+		fen: "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+		nodes: &[44, 1_486, 62_379, 2_103_487, 89_941_194]
+	},
+	ReferencePosition {
+		name: "Position 6",
+		fen: "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+		nodes: &[46, 2_079, 89_890, 3_894_594, 164_075_551, 6_923_051_137]
+	}
+];
+
+/// Counts the leaf nodes of the tree of legal moves from the position down to the given depth
+pub fn perft(position: &Position, depth: u32) -> u64 {
+	if depth == 0 {
+		return 1;
+	}
+	
+	let moves = position.legal_moves();
+	
+	if depth == 1 {
+		return moves.len() as u64;
+	}
+	
+	let mut position = *position;
+	let mut nodes = 0;
+	
+	for mv in moves {
+		let undo = position.make_move(mv);
+		nodes += perft(&position, depth - 1);
+		position.unmake_move(mv, undo);
+	}
+	
+	nodes
+}
+
+/// Same as [`perft`], but split up by the first move, which makes a wrong count easy to narrow down
+/// by comparing it with another move generator
+pub fn divide(position: &Position, depth: u32) -> Vec<(Move, u64)> {
+	position
+		.legal_moves()
+		.into_iter()
+		.map(|mv| (mv, perft(&position.play(mv), depth.saturating_sub(1))))
+		.collect()
+}
+
+impl ReferencePosition {
+	/// Runs [`perft`] at every depth up to `max_depth` that has a published count,
+	/// returning the first depth where the counts differ.
+	/// 
+	/// # Panics
+	/// 
+	/// Panics if the FEN of the position is not valid, which is a mistake in the table
+	pub fn verify(&self, max_depth: u32) -> Result<(), PerftMismatch> {
+		let fen = Fen::parse(self.fen).unwrap_or_else(|error| panic!("Invalid FEN for {}: {error}", self.name));
+		let position = Position::from(fen);
+		
+		for (depth, &expected) in (1..=max_depth).zip(self.nodes) {
+			let found = perft(&position, depth);
+			
+			if found != expected {
+				return Err(PerftMismatch {
+					name: self.name,
+					depth,
+					expected,
+					found
+				});
+			}
+		}
+		
+		Ok(())
+	}
+}
+
+impl Display for PerftMismatch {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"{} at depth {}: expected {} nodes, found {}",
+			self.name,
+			self.depth,
+			self.expected,
+			self.found
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parser::ParseOptions;
+	use crate::variant::Variant;
+	
+	/// Compares perft from the starting position of the variant with the expected counts, starting at depth 1
+	fn check_variant(variant: Variant, nodes: &[u64]) {
+		let position = Position::from(variant.starting_fen());
+		
+		for (depth, &expected) in (1..).zip(nodes) {
+			assert_eq!(perft(&position, depth), expected, "{variant} at depth {depth}");
+		}
+	}
+	
+	/// Compares perft from the position with the expected counts, starting at depth 1
+	fn check_position(fen: Fen, nodes: &[u64]) {
+		let position = Position::from(fen);
+		
+		for (depth, &expected) in (1..).zip(nodes) {
+			assert_eq!(perft(&position, depth), expected, "{fen} at depth {depth}");
+		}
+	}
+	
+	#[test]
+	fn reference_positions() {
+		for reference in REFERENCE_POSITIONS {
+			assert_eq!(reference.verify(3), Ok(()));
+		}
+	}
+	
+	#[test]
+	fn crazyhouse() {
+		check_variant(Variant::Crazyhouse, &[20, 400, 8_902, 197_281]);
+	}
+	
+	#[test]
+	fn crazyhouse_holdings() {
+		// Every piece type in both hands, and a promoted queen that goes back into the hand as a pawn when captured
+		check_position(Fen::parse("2k5/8/8/8/8/8/8/4K3[QRBNPqrbnp] w - - 0 1").unwrap(), &[301, 75_353]);
+		check_position(Fen::parse("4k3/1Q~6/8/8/4b3/8/Kpp5/8/ b - - 0 1").unwrap(), &[20, 360, 5_445]);
+	}
+	
+	#[test]
+	fn three_check() {
+		check_variant(Variant::ThreeCheck, &[20, 400, 8_902, 197_281]);
+	}
+	
+	#[test]
+	fn king_of_the_hill() {
+		check_variant(Variant::KingOfTheHill, &[20, 400, 8_902, 197_281]);
+	}
+	
+	#[test]
+	fn atomic() {
+		check_variant(Variant::Atomic, &[20, 400, 8_902, 197_326]);
+	}
+	
+	#[test]
+	fn antichess() {
+		check_variant(Variant::Antichess, &[20, 400, 8_067, 153_299]);
+	}
+	
+	#[test]
+	fn horde() {
+		check_variant(Variant::Horde, &[8, 128, 1_274, 23_310]);
+	}
+	
+	#[test]
+	fn racing_kings() {
+		check_variant(Variant::RacingKings, &[21, 421, 11_264, 296_242]);
+	}
+	
+	fn variant_fen(variant: Variant, fen: &str) -> Fen {
+		let options = ParseOptions {
+			variant,
+			..ParseOptions::default()
+		};
+		
+		Fen::parse_with(fen, options).unwrap()
+	}
+	
+	#[test]
+	fn chess960_positions() {
+		check_position(Fen::parse("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap(), &[21, 528, 12_189, 326_672]);
+		check_position(Fen::parse("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9").unwrap(), &[21, 807, 18_002, 667_366]);
+		check_position(Fen::parse("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9").unwrap(), &[20, 479, 10_471, 273_318]);
+	}
+	
+	#[test]
+	fn atomic_explosions() {
+		check_position(variant_fen(Variant::Atomic, "rn2kb1r/1pp1p2p/p2q1pp1/3P4/2P3b1/4PN2/PP3PPP/R2QKB1R b KQkq - 0 1"), &[40, 1_238, 45_237]);
+		check_position(variant_fen(Variant::Atomic, "rn1qkb1r/p5pp/2p5/3p4/N3P3/5P2/PPP4P/R1BQK3 w Qkq - 0 1"), &[28, 833, 23_353]);
+		check_position(variant_fen(Variant::Atomic, "8/8/8/8/8/8/2k5/rR4KR w KQ - 0 1"), &[18, 180, 4_364]);
+	}
+	
+	#[test]
+	fn antichess_forced_captures() {
+		check_position(variant_fen(Variant::Antichess, "8/1p6/8/8/8/8/P7/8 w - - 0 1"), &[2, 4, 4, 3]);
+	}
+	
+	#[test]
+	fn horde_position() {
+		check_position(variant_fen(Variant::Horde, "4k3/pp4q1/3P2p1/8/P3PP2/PPP2r2/PPP5/PPPP4 b - - 0 1"), &[30, 241, 6_633]);
+	}
+	
+	/// Small positions where every game ending can be counted by hand
+	#[test]
+	fn variant_endings() {
+		// d4 and e4 reach the hill, so Black has no reply to them
+		check_position(variant_fen(Variant::KingOfTheHill, "8/8/4k3/8/8/4K3/8/8 w - - 0 1"), &[8, 46]);
+		// Ra8+ is White's third check, so Black has no reply to it
+		check_position(variant_fen(Variant::ThreeCheck, "4k3/8/8/8/8/8/8/R3K3 w - - 0 1 +2+0"), &[15, 65]);
+		// The White king reaching the eighth rank wins, since the Black king is too far away to catch up
+		check_position(variant_fen(Variant::RacingKings, "8/6K1/8/8/8/8/k7/8 w - - 0 1"), &[8, 25]);
+	}
+}
@@ -0,0 +1,762 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::{self, BufRead};
+
+use crate::moves::Move;
+use crate::parser::{Fen, FenError, ParseOptions, PieceColor};
+use crate::position::Position;
+use crate::san::SanError;
+use crate::variant::Variant;
+
+/// One game read from a PGN file, with the moves of its main line
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct PgnGame {
+	/// The tag pairs in the order they appear, like `("White", "Carlsen, Magnus")`
+	pub tags: Vec<(String, String)>,
+	/// The position before the first move, taken from the `FEN` tag if there is one,
+	/// in the variant named by the `Variant` tag
+	pub start: Fen,
+	/// The moves of the main line, without any variations
+	pub moves: Vec<Move>,
+	/// The result written at the end of the movetext, if any
+	pub result: Option<GameResult>
+}
+
+/// The outcome of a game as written in PGN
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum GameResult {
+	/// `1-0`
+	WhiteWins,
+	/// `0-1`
+	BlackWins,
+	/// `1/2-1/2`
+	Draw,
+	/// `*`, a game that is still going on or whose result is not known
+	Unknown
+}
+
+/// The reason a game could not be read from a PGN file,
+/// where every `line` is the line number in the whole input starting at 1
+#[derive(Debug)]
+pub enum PgnError {
+	/// Reading the input failed
+	Io(io::Error),
+	/// A tag pair, comment or variation that is not closed, or a character that does not belong in PGN
+	Syntax {
+		line: usize
+	},
+	/// A `FEN` tag that is not valid FEN notation
+	InvalidFen {
+		line: usize,
+		error: FenError
+	},
+	/// A move in the main line that is not legal in its position
+	InvalidMove {
+		line: usize,
+		san: String,
+		error: SanError
+	}
+}
+
+/// Reads games from PGN text one at a time, without holding more than one game in memory.
+///
+/// Every item is one game in the order of the input, an error in one game does not stop
+/// the games after it from being read
+pub struct PgnReader<R> {
+	reader: R,
+	/// The number of lines read so far
+	line: usize,
+	/// A line that starts the next game, read while looking for the end of the last one
+	pending: Option<String>
+}
+
+/// A piece of PGN text, see [`tokenize`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum Token {
+	Tag(String, String),
+	Move(String),
+	Comment(String),
+	/// A numeric annotation glyph like `$1`, or one of the suffixes `!`, `?`, `!!`, `??`, `!?` and `?!`
+	Nag(u8),
+	VariationStart,
+	VariationEnd,
+	Result(GameResult)
+}
+
+impl<R: BufRead> PgnReader<R> {
+	pub fn new(reader: R) -> Self {
+		PgnReader {
+			reader,
+			line: 0,
+			pending: None
+		}
+	}
+	
+	fn read_line(&mut self) -> io::Result<Option<String>> {
+		if let Some(line) = self.pending.take() {
+			return Ok(Some(line));
+		}
+		
+		let mut line = String::new();
+		
+		if self.reader.read_line(&mut line)? == 0 {
+			return Ok(None);
+		}
+		
+		self.line += 1;
+		Ok(Some(line))
+	}
+	
+	/// Collects the text of the next game, from its first tag pair up to the line before the next game's tag pairs,
+	/// along with the line number it starts at
+	fn next_game_text(&mut self) -> io::Result<Option<(String, usize)>> {
+		let mut text = String::new();
+		let mut first_line = self.line + 1;
+		let mut in_movetext = false;
+		let mut in_comment = false;
+		
+		while let Some(line) = self.read_line()? {
+			if text.is_empty() {
+				first_line = self.line;
+			}
+			
+			let trimmed = line.trim_start_matches('\u{feff}').trim();
+			
+			if !in_comment {
+				if line.starts_with('%') {
+					text.push('\n');
+					continue;
+				}
+				
+				if trimmed.starts_with('[') {
+					if in_movetext {
+						self.pending = Some(line);
+						break;
+					}
+				} else if !trimmed.is_empty() {
+					in_movetext = true;
+				}
+			}
+			
+			in_comment = ends_in_comment(&line, in_comment);
+			text.push_str(&line);
+		}
+		
+		if text.trim().is_empty() {
+			return Ok(None);
+		}
+		
+		Ok(Some((text, first_line)))
+	}
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+	type Item = Result<PgnGame, PgnError>;
+	
+	fn next(&mut self) -> Option<Self::Item> {
+		match self.next_game_text() {
+			Ok(Some((text, first_line))) => Some(PgnGame::parse(&text, first_line)),
+			Ok(None) => None,
+			Err(error) => Some(Err(PgnError::Io(error)))
+		}
+	}
+}
+
+impl PgnGame {
+	/// A game without any tag pairs or result, playing the moves from the given position
+	pub fn new(start: Fen, moves: Vec<Move>) -> Self {
+		PgnGame {
+			tags: Vec::new(),
+			start,
+			moves,
+			result: None
+		}
+	}
+	
+	/// Writes the game in PGN export format, see [`write_tags`] and [`MovetextWriter`]
+	pub fn to_pgn(&self) -> String {
+		let result = self.result.unwrap_or(GameResult::Unknown);
+		let mut pgn = write_tags(&self.tags, &self.start, result);
+		let mut movetext = MovetextWriter::new();
+		let mut position = Position::from(self.start);
+		
+		for &mv in &self.moves {
+			movetext.push_move(&position, mv);
+			position.make_move(mv);
+		}
+		
+		movetext.push_token(&result.to_string());
+		pgn.push_str(&movetext.finish());
+		pgn
+	}
+	
+	/// Reads the first game from PGN text
+	pub fn from_pgn(text: &str) -> Result<Self, PgnError> {
+		match PgnReader::new(text.as_bytes()).next() {
+			Some(game) => game,
+			None => Err(PgnError::Syntax {
+				line: 1
+			})
+		}
+	}
+	
+	/// Reads one game, where `first_line` is the line number the text starts at in the whole input
+	fn parse(text: &str, first_line: usize) -> Result<Self, PgnError> {
+		let tokens = tokenize(text, first_line)?;
+		let (tags, start) = read_tags(&tokens)?;
+		let mut position = Position::from(start);
+		let mut moves = Vec::new();
+		let mut result = None;
+		let mut depth = 0;
+		
+		for (line, token) in tokens {
+			match token {
+				Token::VariationStart => depth += 1,
+				Token::VariationEnd if depth == 0 => return Err(PgnError::Syntax {
+					line
+				}),
+				Token::VariationEnd => depth -= 1,
+				Token::Move(san) if depth == 0 => {
+					let mv = position.parse_san(&san).map_err(|error| PgnError::InvalidMove {
+						line,
+						san,
+						error
+					})?;
+					
+					position.make_move(mv);
+					moves.push(mv);
+				}
+				Token::Result(_) if depth > 0 => return Err(PgnError::Syntax {
+					line
+				}),
+				Token::Result(game_result) => result = Some(game_result),
+				_ => {}
+			}
+		}
+		
+		if depth > 0 {
+			return Err(PgnError::Syntax {
+				line: first_line + text.lines().count().saturating_sub(1)
+			});
+		}
+		
+		Ok(PgnGame {
+			tags,
+			start,
+			moves,
+			result
+		})
+	}
+	
+	/// The value of the first tag pair with the given name
+	pub fn tag(&self, name: &str) -> Option<&str> {
+		self.tags
+			.iter()
+			.find(|(tag, _)| tag == name)
+			.map(|(_, value)| value.as_str())
+	}
+	
+	/// The position before the first move and after every move of the main line
+	pub fn positions(&self) -> Vec<Fen> {
+		let mut position = Position::from(self.start);
+		let mut positions = Vec::with_capacity(self.moves.len() + 1);
+		positions.push(self.start);
+		
+		for &mv in &self.moves {
+			position.make_move(mv);
+			positions.push(Fen::from(position));
+		}
+		
+		positions
+	}
+}
+
+/// The seven tags every exported game starts with, in the order they have to appear,
+/// along with the value used when a game does not have them
+pub const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+	("Event", "?"),
+	("Site", "?"),
+	("Date", "????.??.??"),
+	("Round", "?"),
+	("White", "?"),
+	("Black", "?"),
+	("Result", "*")
+];
+
+/// The longest a line of exported movetext can be
+pub const LINE_LENGTH: usize = 80;
+
+/// Writes movetext tokens separated by spaces, starting a new line before a token
+/// that would go past [`LINE_LENGTH`]
+pub(crate) struct MovetextWriter {
+	output: String,
+	line_length: usize,
+	/// Whether the next Black move needs its move number, like at the start or after a comment
+	needs_number: bool,
+	/// Text written right in front of the next token without a space, like an opening `(`
+	prefix: String
+}
+
+impl MovetextWriter {
+	pub(crate) fn new() -> Self {
+		MovetextWriter {
+			output: String::new(),
+			line_length: 0,
+			needs_number: true,
+			prefix: String::new()
+		}
+	}
+	
+	/// Writes one token, like a move, a comment or a result
+	pub(crate) fn push_token(&mut self, token: &str) {
+		let token = format!("{}{token}", self.prefix);
+		self.prefix.clear();
+		let length = token.chars().count();
+		
+		if self.line_length > 0 && self.line_length + 1 + length > LINE_LENGTH {
+			self.output.push('\n');
+			self.line_length = 0;
+		} else if self.line_length > 0 {
+			self.output.push(' ');
+			self.line_length += 1;
+		}
+		
+		self.output.push_str(&token);
+		self.line_length += length;
+	}
+	
+	/// Writes a move in SAN, with its move number if it is a White move or a Black move that needs one
+	pub(crate) fn push_move(&mut self, position: &Position, mv: Move) {
+		if position.side_to_move != PieceColor::Black {
+			self.push_token(&format!("{}.", position.fullmove_number));
+		} else if self.needs_number {
+			self.push_token(&format!("{}...", position.fullmove_number));
+		}
+		
+		self.push_token(&position.san(mv));
+		self.needs_number = false;
+	}
+	
+	/// Writes a comment in braces, split over lines at its spaces if it is too long
+	pub(crate) fn push_comment(&mut self, comment: &str) {
+		let words: Vec<&str> = comment.split_whitespace().collect();
+		
+		match words.as_slice() {
+			[] => self.push_token("{}"),
+			[word] => self.push_token(&format!("{{{word}}}")),
+			[first, middle @ .., last] => {
+				self.push_token(&format!("{{{first}"));
+				for word in middle {
+					self.push_token(word);
+				}
+				self.push_token(&format!("{last}}}"));
+			}
+		}
+		
+		self.needs_number = true;
+	}
+	
+	/// Writes a numeric annotation glyph like `$1`
+	pub(crate) fn push_nag(&mut self, nag: u8) {
+		self.push_token(&format!("${nag}"));
+	}
+	
+	/// Starts a variation, which is written right in front of its first move like `(3... Nf6`
+	pub(crate) fn open_variation(&mut self) {
+		self.prefix.push('(');
+		self.needs_number = true;
+	}
+	
+	/// Ends a variation, written right after its last token
+	pub(crate) fn close_variation(&mut self) {
+		if self.line_length + 1 > LINE_LENGTH {
+			self.output.push('\n');
+			self.line_length = 0;
+		}
+		
+		self.output.push(')');
+		self.line_length += 1;
+		self.needs_number = true;
+	}
+	
+	/// The written movetext, ending with a newline
+	pub(crate) fn finish(mut self) -> String {
+		self.output.push('\n');
+		self.output
+	}
+}
+
+/// Writes the tag pair section of an exported game, followed by the empty line before the movetext.
+/// 
+/// The seven tag roster comes first, with `?` values for tags the game does not have,
+/// followed by a `Variant` tag if the game is not standard chess and the tags do not name it,
+/// `SetUp` and `FEN` tags if `start` is not the variant's starting position,
+/// and then every other tag in the order given
+pub fn write_tags(tags: &[(String, String)], start: &Fen, result: GameResult) -> String {
+	let find = |name: &str| {
+		tags.iter()
+			.find(|(tag, _)| tag == name)
+			.map(|(_, value)| value.clone())
+	};
+	let mut pgn = String::new();
+	
+	for (name, default) in SEVEN_TAG_ROSTER {
+		let value = match name {
+			"Result" => result.to_string(),
+			_ => find(name).unwrap_or_else(|| default.to_string())
+		};
+		
+		pgn.push_str(&write_tag(name, &value));
+	}
+	
+	if start.variant != Variant::Standard && find("Variant").is_none() {
+		pgn.push_str(&write_tag("Variant", start.variant.name()));
+	}
+	
+	if *start != start.variant.starting_fen() {
+		pgn.push_str(&write_tag("SetUp", "1"));
+		pgn.push_str(&write_tag("FEN", &start.to_string()));
+	}
+	
+	for (name, value) in tags {
+		let written = SEVEN_TAG_ROSTER.iter().any(|(roster_name, _)| roster_name == name)
+			|| name == "SetUp"
+			|| name == "FEN";
+		
+		if !written {
+			pgn.push_str(&write_tag(name, value));
+		}
+	}
+	
+	pgn.push('\n');
+	pgn
+}
+
+/// Writes one tag pair line, escaping quotes and backslashes in the value
+fn write_tag(name: &str, value: &str) -> String {
+	let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+	format!("[{name} \"{value}\"]\n")
+}
+
+impl GameResult {
+	/// Reads a result token like `1-0`, returning `None` for anything else
+	pub fn from_pgn(token: &str) -> Option<Self> {
+		match token {
+			"1-0" => Some(GameResult::WhiteWins),
+			"0-1" => Some(GameResult::BlackWins),
+			"1/2-1/2" => Some(GameResult::Draw),
+			"*" => Some(GameResult::Unknown),
+			_ => None
+		}
+	}
+}
+
+/// Collects the tag pairs in front of the movetext of a game and the starting position they describe,
+/// which is the `FEN` tag if there is one and the starting position of the variant otherwise.
+/// 
+/// The variant comes from the `Variant` tag, where a missing or unknown name means standard chess.
+/// The `FEN` tag is used whether or not the game also has the `[SetUp "1"]` tag that should go with it
+pub(crate) fn read_tags(tokens: &[(usize, Token)]) -> Result<(Vec<(String, String)>, Fen), PgnError> {
+	let mut tags = Vec::new();
+	let mut fen = None;
+	
+	for (line, token) in tokens {
+		let Token::Tag(name, value) = token else {
+			break;
+		};
+		
+		if name == "FEN" {
+			fen = Some((*line, value));
+		}
+		
+		tags.push((name.clone(), value.clone()));
+	}
+	
+	let variant = tags
+		.iter()
+		.find(|(name, _)| name == "Variant")
+		.and_then(|(_, value)| Variant::from_name(value))
+		.unwrap_or_default();
+	let options = ParseOptions {
+		variant,
+		..ParseOptions::default()
+	};
+	
+	let start = match fen {
+		Some((line, value)) => Fen::parse_with(value, options).map_err(|error| PgnError::InvalidFen {
+			line,
+			error
+		})?,
+		None => variant.starting_fen()
+	};
+	
+	Ok((tags, start))
+}
+
+/// Splits the text of one game into tokens along with the line number each one starts at,
+/// skipping move numbers and `;` comments
+pub(crate) fn tokenize(text: &str, first_line: usize) -> Result<Vec<(usize, Token)>, PgnError> {
+	let mut tokens = Vec::new();
+	let mut characters = text.chars().peekable();
+	let mut line = first_line;
+	
+	while let Some(character) = characters.next() {
+		let token_line = line;
+		
+		let token = match character {
+			'\n' => {
+				line += 1;
+				continue;
+			}
+			'[' => {
+				let mut name = String::new();
+				while let Some(&next) = characters.peek() {
+					if next.is_whitespace() || next == '"' {
+						break;
+					}
+					name.push(next);
+					characters.next();
+				}
+				
+				while characters.peek().is_some_and(|next| next.is_whitespace() && *next != '\n') {
+					characters.next();
+				}
+				
+				if characters.next() != Some('"') {
+					return Err(PgnError::Syntax {
+						line
+					});
+				}
+				
+				let mut value = String::new();
+				loop {
+					match characters.next() {
+						Some('\\') => value.extend(characters.next()),
+						Some('"') => break,
+						Some('\n') | None => return Err(PgnError::Syntax {
+							line
+						}),
+						Some(next) => value.push(next)
+					}
+				}
+				
+				while characters.peek().is_some_and(|next| next.is_whitespace() && *next != '\n') {
+					characters.next();
+				}
+				
+				if characters.next() != Some(']') {
+					return Err(PgnError::Syntax {
+						line
+					});
+				}
+				
+				Token::Tag(name, value)
+			}
+			'{' => {
+				let mut comment = String::new();
+				loop {
+					match characters.next() {
+						Some('}') => break,
+						Some(next) => {
+							if next == '\n' {
+								line += 1;
+							}
+							comment.push(next);
+						}
+						None => return Err(PgnError::Syntax {
+							line: token_line
+						})
+					}
+				}
+				
+				Token::Comment(comment.trim().to_string())
+			}
+			';' => {
+				for next in characters.by_ref() {
+					if next == '\n' {
+						line += 1;
+						break;
+					}
+				}
+				continue;
+			}
+			'(' => Token::VariationStart,
+			')' => Token::VariationEnd,
+			'*' => Token::Result(GameResult::Unknown),
+			'$' => {
+				let mut number = String::new();
+				while let Some(&next) = characters.peek().filter(|next| next.is_ascii_digit()) {
+					number.push(next);
+					characters.next();
+				}
+				
+				Token::Nag(number.parse().map_err(|_| PgnError::Syntax {
+					line
+				})?)
+			}
+			'!' | '?' => {
+				let mut suffix = String::from(character);
+				while let Some(&next) = characters.peek().filter(|next| matches!(next, '!' | '?')) {
+					suffix.push(next);
+					characters.next();
+				}
+				
+				let nag = match suffix.as_str() {
+					"!" => 1,
+					"?" => 2,
+					"!!" => 3,
+					"??" => 4,
+					"!?" => 5,
+					"?!" => 6,
+					_ => return Err(PgnError::Syntax {
+						line
+					})
+				};
+				
+				Token::Nag(nag)
+			}
+			'.' => continue,
+			_ if character.is_whitespace() || character == '\u{feff}' => continue,
+			_ if character.is_ascii_alphanumeric() => {
+				let mut symbol = String::from(character);
+				while let Some(&next) = characters.peek() {
+					if !(next.is_ascii_alphanumeric() || "_+#=:-/".contains(next)) {
+						break;
+					}
+					symbol.push(next);
+					characters.next();
+				}
+				
+				// The `e.p.` some writers put after an en passant capture, which is not part of the move
+				if symbol == "e" && characters.clone().take(3).eq(".p.".chars()) {
+					characters.nth(2);
+					continue;
+				}
+				
+				if let Some(result) = GameResult::from_pgn(&symbol) {
+					Token::Result(result)
+				} else if symbol.chars().all(|next| next.is_ascii_digit()) {
+					continue;
+				} else {
+					Token::Move(symbol)
+				}
+			}
+			_ => return Err(PgnError::Syntax {
+				line
+			})
+		};
+		
+		tokens.push((token_line, token));
+	}
+	
+	Ok(tokens)
+}
+
+/// Whether a `{` comment is still open at the end of the line, given whether one was open at its start
+fn ends_in_comment(line: &str, mut in_comment: bool) -> bool {
+	let mut in_string = false;
+	
+	for character in line.chars() {
+		match character {
+			'}' if in_comment => in_comment = false,
+			'{' if !in_comment && !in_string => in_comment = true,
+			'"' if !in_comment => in_string = !in_string,
+			';' if !in_comment && !in_string => break,
+			_ => {}
+		}
+	}
+	
+	in_comment
+}
+
+impl Display for GameResult {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			GameResult::WhiteWins => write!(f, "1-0"),
+			GameResult::BlackWins => write!(f, "0-1"),
+			GameResult::Draw => write!(f, "1/2-1/2"),
+			GameResult::Unknown => write!(f, "*")
+		}
+	}
+}
+
+impl Display for PgnGame {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.to_pgn())
+	}
+}
+
+impl Display for PgnError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			PgnError::Io(error) => write!(f, "could not read PGN: {error}"),
+			PgnError::Syntax { line } => write!(f, "invalid PGN on line {line}"),
+			PgnError::InvalidFen { line, error } => write!(f, "invalid FEN tag on line {line}: {error}"),
+			PgnError::InvalidMove { line, san, error } => write!(f, "move {san} on line {line}: {error}")
+		}
+	}
+}
+
+impl Error for PgnError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			PgnError::Io(error) => Some(error),
+			PgnError::InvalidFen { error, .. } => Some(error),
+			PgnError::InvalidMove { error, .. } => Some(error),
+			PgnError::Syntax { .. } => None
+		}
+	}
+}
+
+impl From<io::Error> for PgnError {
+	fn from(error: io::Error) -> Self {
+		PgnError::Io(error)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::moves::MoveKind;
+	
+	const ANNOTATED: &str = r#"[Event "Casual game"]
+[Site "London"]
+[Date "1851.06.21"]
+[Round "?"]
+[White "Anderssen, Adolf"]
+[Black "Kieseritzky, Lionel"]
+[Result "1-0"]
+
+{Opening comment} 1. e4 {King pawn} e5 2. f4 $1 (2. Nf3 Nc6 (2... d6 {Philidor}) 3. Bb5 $2) 2... exf4
+3. Bc4 Qh4+ 4. Kf1 b5 $5 (4... d6 5. Nc3) 5. Bxb5 Nf6 1-0
+"#;
+	
+	#[test]
+	fn unclosed_variation() {
+		let before_result = "1. e4 e5 (1... c5 2. Nf3 1-0";
+		let at_end = "[Event \"?\"]\n\n1. e4 e5\n(1... c5 2. Nf3\n";
+		
+		assert!(matches!(PgnGame::from_pgn(before_result), Err(PgnError::Syntax { line: 1 })));
+		assert!(matches!(PgnGame::from_pgn(at_end), Err(PgnError::Syntax { line: 4 })));
+	}
+	
+	#[test]
+	fn en_passant_suffix() {
+		let game = PgnGame::from_pgn("1. e4 d5 2. e5 f5 3. exf6 e.p. Nxf6 *").unwrap();
+		
+		assert_eq!(game.moves.len(), 6);
+		assert_eq!(game.moves[4].kind, MoveKind::EnPassant);
+	}
+	
+	#[test]
+	fn round_trip() {
+		let game = PgnGame::from_pgn(ANNOTATED).unwrap();
+		let written = game.to_pgn();
+		
+		assert_eq!(game.moves.len(), 10);
+		assert_eq!(game.tag("White"), Some("Anderssen, Adolf"));
+		assert_eq!(game.result, Some(GameResult::WhiteWins));
+		assert_eq!(PgnGame::from_pgn(&written).unwrap(), game);
+		assert!(written.ends_with("5. Bxb5 Nf6 1-0\n"));
+	}
+}
@@ -0,0 +1,571 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::moves::{Move, MoveKind};
+use crate::parser::{Fen, PieceColor, PieceType};
+use crate::pgn::{GameResult, PgnGame};
+use crate::position::Position;
+use crate::random::Random;
+use crate::square::Square;
+use crate::uci::CastlingMode;
+
+/// The size of one entry in a Polyglot `.bin` file
+pub const ENTRY_SIZE: usize = 16;
+
+/// One entry of a Polyglot opening book, exactly as it is stored in the file
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PolyglotEntry {
+	/// The [`Position::polyglot_key`] of the position the move is played in
+	pub key: u64,
+	/// The move, with the target square in bits 0 to 5, the starting square in bits 6 to 11
+	/// and the promotion piece in bits 12 to 14, counting 1 for a knight up to 4 for a queen.
+	/// Castling is written as the king moving onto its own rook, like `e1h1`
+	pub raw_move: u16,
+	/// How good the move is compared to the other moves of the position, where 0 means it should not be played
+	pub weight: u16,
+	/// Left for programs that learn from their games, usually 0
+	pub learn: u32
+}
+
+/// A move suggested by an opening book, with its weight and learn value
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct BookMove {
+	pub mv: Move,
+	pub weight: u16,
+	pub learn: u32
+}
+
+/// An opening book in the Polyglot `.bin` format, held in memory
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct OpeningBook {
+	/// Sorted by key, like in the file
+	entries: Vec<PolyglotEntry>
+}
+
+/// Which games and moves go into a book made with [`BookBuilder`], and how their results are weighted
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct BookOptions {
+	/// Leaves out games where either player has no `WhiteElo` or `BlackElo` tag or is rated lower
+	pub min_elo: Option<u32>,
+	/// Leaves out the moves after this many plies of every game
+	pub max_ply: Option<usize>,
+	/// Only takes the moves of the side that won, leaving out drawn games completely
+	pub winning_side_only: bool,
+	/// The weight a move gets for every game won by the side that played it
+	pub win_points: u16,
+	/// The weight a move gets for every drawn game it was played in
+	pub draw_points: u16,
+	/// The weight a move gets for every game lost by the side that played it
+	pub loss_points: u16
+}
+
+/// Collects the moves of many games into an [`OpeningBook`],
+/// adding up the weight every move earns from the results of the games it was played in
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct BookBuilder {
+	options: BookOptions,
+	/// The total weight of every move, by the key of its position and the move as Polyglot stores it
+	weights: HashMap<(u64, u16), u64>
+}
+
+impl Default for BookOptions {
+	/// Every game and every move, with 2 points for a win, 1 for a draw and 0 for a loss like Polyglot itself
+	fn default() -> Self {
+		BookOptions {
+			min_elo: None,
+			max_ply: None,
+			winning_side_only: false,
+			win_points: 2,
+			draw_points: 1,
+			loss_points: 0
+		}
+	}
+}
+
+impl BookBuilder {
+	pub fn new(options: BookOptions) -> Self {
+		BookBuilder {
+			options,
+			weights: HashMap::new()
+		}
+	}
+	
+	/// Adds the main line moves of the game, returning whether the [`BookOptions`] let it in.
+	/// Games without a result of `1-0`, `0-1` or `1/2-1/2` are left out, since there is nothing to weight their moves by
+	pub fn add_game(&mut self, game: &PgnGame) -> bool {
+		let winner = match game.result {
+			Some(GameResult::WhiteWins) => Some(PieceColor::White),
+			Some(GameResult::BlackWins) => Some(PieceColor::Black),
+			Some(GameResult::Draw) if !self.options.winning_side_only => None,
+			_ => return false
+		};
+		
+		if let Some(min_elo) = self.options.min_elo {
+			let rated = |tag| game.tag(tag).and_then(|elo| elo.trim().parse::<u32>().ok()).is_some_and(|elo| elo >= min_elo);
+			if !rated("WhiteElo") || !rated("BlackElo") {
+				return false;
+			}
+		}
+		
+		let max_ply = self.options.max_ply.unwrap_or(usize::MAX);
+		let mut position = Position::from(game.start);
+		
+		for &mv in game.moves.iter().take(max_ply) {
+			let us = position.side_to_move;
+			let points = match winner {
+				Some(color) if color == us => self.options.win_points,
+				Some(_) => self.options.loss_points,
+				None => self.options.draw_points
+			};
+			
+			if !self.options.winning_side_only || winner == Some(us) {
+				let key = (position.polyglot_key(), PolyglotEntry::encode_move(&position, mv));
+				*self.weights.entry(key).or_insert(0) += u64::from(points);
+			}
+			
+			position.make_move(mv);
+		}
+		
+		true
+	}
+	
+	/// The book with every move added so far, sorted by key and then by weight from high to low.
+	/// 
+	/// When the weights do not fit in the 16 bits an entry has, all of them are scaled down by the same factor,
+	/// keeping every move that earned any weight at 1 or more
+	pub fn build(&self) -> OpeningBook {
+		let max = self.weights.values().copied().max().unwrap_or(0);
+		let scale = |weight: u64| {
+			if max <= u64::from(u16::MAX) {
+				weight as u16
+			} else {
+				(weight * u64::from(u16::MAX) / max).max(u64::from(weight > 0)) as u16
+			}
+		};
+		
+		let mut entries: Vec<PolyglotEntry> = self
+			.weights
+			.iter()
+			.map(|(&(key, raw_move), &weight)| PolyglotEntry {
+				key,
+				raw_move,
+				weight: scale(weight),
+				learn: 0
+			})
+			.collect();
+		
+		entries.sort_by(|a, b| a.key.cmp(&b.key).then(b.weight.cmp(&a.weight)).then(a.raw_move.cmp(&b.raw_move)));
+		
+		OpeningBook::new(entries)
+	}
+}
+
+impl PolyglotEntry {
+	/// Reads an entry from its 16 bytes in the file, where every number is big-endian
+	pub fn from_bytes(bytes: [u8; ENTRY_SIZE]) -> Self {
+		let [k0, k1, k2, k3, k4, k5, k6, k7, m0, m1, w0, w1, l0, l1, l2, l3] = bytes;
+		
+		PolyglotEntry {
+			key: u64::from_be_bytes([k0, k1, k2, k3, k4, k5, k6, k7]),
+			raw_move: u16::from_be_bytes([m0, m1]),
+			weight: u16::from_be_bytes([w0, w1]),
+			learn: u32::from_be_bytes([l0, l1, l2, l3])
+		}
+	}
+	
+	/// The 16 bytes of the entry in the file
+	pub fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+		let mut bytes = [0; ENTRY_SIZE];
+		bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+		bytes[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+		bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+		bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+		bytes
+	}
+	
+	/// The legal move the entry stands for in the position, `None` if it is not legal there
+	pub fn decode_move(&self, position: &Position) -> Option<Move> {
+		let to = Square::from_index(usize::from(self.raw_move & 63))?;
+		let from = Square::from_index(usize::from((self.raw_move >> 6) & 63))?;
+		let promotion = match (self.raw_move >> 12) & 7 {
+			1 => "n",
+			2 => "b",
+			3 => "r",
+			4 => "q",
+			_ => ""
+		};
+		
+		position.parse_uci(&format!("{from}{to}{promotion}"), CastlingMode::Chess960).ok()
+	}
+	
+	/// Writes a legal move of the position the way Polyglot stores it
+	pub fn encode_move(position: &Position, mv: Move) -> u16 {
+		let to = match mv.kind {
+			MoveKind::Castle(side) => position.castling_rook(position.side_to_move, side).unwrap_or(mv.to),
+			_ => mv.to
+		};
+		let promotion = match mv.kind {
+			MoveKind::Promotion(PieceType::Knight) => 1,
+			MoveKind::Promotion(PieceType::Bishop) => 2,
+			MoveKind::Promotion(PieceType::Rook) => 3,
+			MoveKind::Promotion(PieceType::Queen) => 4,
+			_ => 0
+		};
+		
+		(promotion << 12) | ((mv.from.index() as u16) << 6) | to.index() as u16
+	}
+}
+
+impl OpeningBook {
+	/// A book with the given entries, which do not need to be sorted
+	pub fn new(mut entries: Vec<PolyglotEntry>) -> Self {
+		entries.sort_by_key(|entry| entry.key);
+		
+		OpeningBook {
+			entries
+		}
+	}
+	
+	/// Reads a whole book in the Polyglot `.bin` format
+	pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+		let mut bytes = Vec::new();
+		reader.read_to_end(&mut bytes)?;
+		
+		if bytes.len() % ENTRY_SIZE != 0 {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("{} bytes is not a whole number of {ENTRY_SIZE} byte Polyglot entries", bytes.len())
+			));
+		}
+		
+		let entries = bytes
+			.chunks_exact(ENTRY_SIZE)
+			.map(|chunk| {
+				let mut entry = [0; ENTRY_SIZE];
+				entry.copy_from_slice(chunk);
+				PolyglotEntry::from_bytes(entry)
+			})
+			.collect();
+		
+		Ok(OpeningBook::new(entries))
+	}
+	
+	/// Reads a whole book from a Polyglot `.bin` file
+	pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		OpeningBook::read(BufReader::new(File::open(path)?))
+	}
+	
+	/// Writes the book in the Polyglot `.bin` format
+	pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+		for entry in &self.entries {
+			writer.write_all(&entry.to_bytes())?;
+		}
+		
+		writer.flush()
+	}
+	
+	/// Writes the book to a Polyglot `.bin` file, replacing the file if it exists
+	pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+		self.write(BufWriter::new(File::create(path)?))
+	}
+	
+	/// Every entry of the book, sorted by key
+	pub fn entries(&self) -> &[PolyglotEntry] {
+		&self.entries
+	}
+	
+	/// The entries for the position with the given Polyglot key, in the order of the file
+	pub fn entries_for(&self, key: u64) -> &[PolyglotEntry] {
+		let start = self.entries.partition_point(|entry| entry.key < key);
+		let end = self.entries.partition_point(|entry| entry.key <= key);
+		&self.entries[start..end]
+	}
+	
+	/// The book moves for the position, leaving out any entry whose move is not legal there
+	pub fn moves(&self, fen: &Fen) -> Vec<BookMove> {
+		let position = Position::from(fen);
+		
+		self.entries_for(position.polyglot_key())
+			.iter()
+			.filter_map(|entry| {
+				Some(BookMove {
+					mv: entry.decode_move(&position)?,
+					weight: entry.weight,
+					learn: entry.learn
+				})
+			})
+			.collect()
+	}
+	
+	/// The book move with the highest weight, the first one in the file if several share it
+	pub fn best_move(&self, fen: &Fen) -> Option<BookMove> {
+		self.moves(fen)
+			.into_iter()
+			.filter(|book_move| book_move.weight > 0)
+			.reduce(|best, book_move| if book_move.weight > best.weight { book_move } else { best })
+	}
+	
+	/// A book move picked at random, each move being as likely as its share of the total weight.
+	/// Moves with a weight of 0 are never picked
+	pub fn weighted_move(&self, fen: &Fen, random: &mut Random) -> Option<BookMove> {
+		let moves = self.moves(fen);
+		let total: u64 = moves.iter().map(|book_move| u64::from(book_move.weight)).sum();
+		
+		if total == 0 {
+			return None;
+		}
+		
+		let mut pick = random.below(total);
+		
+		moves.into_iter().find(|book_move| {
+			let weight = u64::from(book_move.weight);
+			if pick < weight {
+				return true;
+			}
+			pick -= weight;
+			false
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::pgn::PgnReader;
+	
+	/// Four games: a White win between rated players, a Black win where White is rated lower,
+	/// a draw without ratings and an unfinished game
+	const GAMES: &str = "[WhiteElo \"2400\"]
+[BlackElo \"2300\"]
+
+1. e4 e5 2. Nf3 Nc6 1-0
+
+[WhiteElo \"2000\"]
+[BlackElo \"2500\"]
+
+1. e4 c5 2. Nf3 d6 0-1
+
+[Event \"?\"]
+
+1. d4 d5 1/2-1/2
+
+[Event \"?\"]
+
+1. c4 *
+";
+	
+	/// The Polyglot key of the starting position
+	const START: u64 = 0x463b_9618_1691_fc9c;
+	
+	fn position(fen: &str) -> Position {
+		Position::from(Fen::parse(fen).unwrap())
+	}
+	
+	fn entry(key: u64, raw_move: u16, weight: u16) -> PolyglotEntry {
+		PolyglotEntry {
+			key,
+			raw_move,
+			weight,
+			learn: 0
+		}
+	}
+	
+	/// A book for the starting position with e4, d4 three times as often, Nf3 at weight 0 and an illegal move,
+	/// mixed in with an entry for some other position
+	fn start_book() -> OpeningBook {
+		OpeningBook::new(vec![
+			entry(START + 1, 0x031c, 50),
+			entry(START, 0x031c, 10),
+			entry(START, 0x02db, 30),
+			entry(START, 0x0195, 0),
+			entry(START, 0x0324, 40),
+			entry(START - 1, 0x02db, 50)
+		])
+	}
+	
+	#[test]
+	fn entry_bytes() {
+		let entry = PolyglotEntry {
+			key: START,
+			raw_move: 0x031c,
+			weight: 0x0102,
+			learn: 0x0304_0506
+		};
+		let bytes = [0x46, 0x3b, 0x96, 0x18, 0x16, 0x91, 0xfc, 0x9c, 0x03, 0x1c, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
+		
+		assert_eq!(entry.to_bytes(), bytes);
+		assert_eq!(PolyglotEntry::from_bytes(bytes), entry);
+	}
+	
+	#[test]
+	fn read() {
+		let book = start_book();
+		let bytes: Vec<u8> = book.entries().iter().flat_map(|entry| entry.to_bytes()).collect();
+		
+		assert_eq!(bytes.len(), 6 * ENTRY_SIZE);
+		assert_eq!(OpeningBook::read(bytes.as_slice()).unwrap(), book);
+		assert!(OpeningBook::read(&bytes[..ENTRY_SIZE + 1]).is_err());
+	}
+	
+	#[test]
+	fn move_encoding() {
+		let castling = position("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+		let promotion = position("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+		let cases = [
+			(Position::default(), "e2e4", 0x031c),
+			(castling, "e1g1", (4 << 6) | 7),
+			(castling, "e1c1", 4 << 6),
+			(promotion, "a7a8n", (1 << 12) | (48 << 6) | 56),
+			(promotion, "a7a8b", (2 << 12) | (48 << 6) | 56),
+			(promotion, "a7a8r", (3 << 12) | (48 << 6) | 56),
+			(promotion, "a7b8q", (4 << 12) | (48 << 6) | 57)
+		];
+		
+		for (position, uci, raw_move) in cases {
+			let mv = position.parse_uci(uci, CastlingMode::Standard).unwrap();
+			
+			assert_eq!(PolyglotEntry::encode_move(&position, mv), raw_move, "{uci}");
+			assert_eq!(entry(position.polyglot_key(), raw_move, 1).decode_move(&position), Some(mv), "{uci}");
+		}
+		
+		assert_eq!(entry(START, 0x0324, 1).decode_move(&Position::default()), None);
+	}
+	
+	#[test]
+	fn lookup() {
+		let book = start_book();
+		let start = Fen::default();
+		
+		assert_eq!(Position::default().polyglot_key(), START);
+		assert_eq!(book.entries_for(START).len(), 4);
+		assert!(book.entries_for(START + 2).is_empty());
+		
+		// The illegal e2e5 is left out
+		assert_eq!(book.moves(&start).len(), 3);
+		
+		let best = book.best_move(&start).unwrap();
+		assert_eq!(best.weight, 30);
+		assert_eq!(best.mv, Position::default().parse_uci("d2d4", CastlingMode::Standard).unwrap());
+		
+		assert_eq!(OpeningBook::default().best_move(&start), None);
+	}
+	
+	#[test]
+	fn weighted_move() {
+		let book = start_book();
+		let start = Fen::default();
+		let mut random = Random::new(7);
+		let mut d4 = 0;
+		
+		// Nf3 has a weight of 0 and is never picked, and d4 should come up about three times as often as e4
+		for _ in 0..1000 {
+			match book.weighted_move(&start, &mut random).unwrap().weight {
+				30 => d4 += 1,
+				weight => assert_eq!(weight, 10)
+			}
+		}
+		
+		assert!((650..850).contains(&d4), "{d4}");
+		
+		let first = book.weighted_move(&start, &mut Random::new(7));
+		assert_eq!(book.weighted_move(&start, &mut Random::new(7)), first);
+		assert_eq!(OpeningBook::new(vec![entry(START, 0x0195, 0)]).weighted_move(&start, &mut random), None);
+	}
+	
+	fn games() -> Vec<PgnGame> {
+		PgnReader::new(GAMES.as_bytes()).map(Result::unwrap).collect()
+	}
+	
+	fn build(options: BookOptions, games: &[PgnGame]) -> (OpeningBook, Vec<bool>) {
+		let mut builder = BookBuilder::new(options);
+		let added = games.iter().map(|game| builder.add_game(game)).collect();
+		(builder.build(), added)
+	}
+	
+	/// The moves of the book for the position after the moves, with their weights
+	fn weights(book: &OpeningBook, moves: &str) -> Vec<(String, u16)> {
+		let mut position = Position::default();
+		for san in moves.split_whitespace() {
+			position.make_move(position.parse_san(san).unwrap());
+		}
+		
+		book.moves(&Fen::from(position)).iter().map(|book_move| (book_move.mv.to_string(), book_move.weight)).collect()
+	}
+	
+	fn moves(list: &[(&str, u16)]) -> Vec<(String, u16)> {
+		list.iter().map(|&(mv, weight)| (mv.to_string(), weight)).collect()
+	}
+	
+	#[test]
+	fn build_from_games() {
+		let (book, added) = build(BookOptions::default(), &games());
+		
+		assert_eq!(added, [true, true, true, false]);
+		assert_eq!(book.entries().len(), 9);
+		assert_eq!(weights(&book, ""), moves(&[("e2e4", 2), ("d2d4", 1)]));
+		assert_eq!(weights(&book, "e4"), moves(&[("c7c5", 2), ("e7e5", 0)]));
+		assert_eq!(weights(&book, "d4"), moves(&[("d7d5", 1)]));
+		assert!(book.entries().windows(2).all(|pair| (pair[0].key, u16::MAX - pair[0].weight) <= (pair[1].key, u16::MAX - pair[1].weight)));
+		
+		let mut bytes = Vec::new();
+		book.write(&mut bytes).unwrap();
+		let read = OpeningBook::read(bytes.as_slice()).unwrap();
+		
+		assert_eq!(read, book);
+		assert_eq!(read.best_move(&Fen::default()).map(|book_move| book_move.mv.to_string()), Some("e2e4".to_string()));
+	}
+	
+	#[test]
+	fn filters() {
+		let games = games();
+		
+		let rated = BookOptions {
+			min_elo: Some(2200),
+			..BookOptions::default()
+		};
+		let (book, added) = build(rated, &games);
+		assert_eq!(added, [true, false, false, false]);
+		assert_eq!(book.entries().len(), 4);
+		
+		let opening = BookOptions {
+			max_ply: Some(1),
+			..BookOptions::default()
+		};
+		let (book, _) = build(opening, &games);
+		assert_eq!(book.entries().len(), 2);
+		assert!(weights(&book, "e4").is_empty());
+		
+		let winners = BookOptions {
+			winning_side_only: true,
+			..BookOptions::default()
+		};
+		let (book, added) = build(winners, &games);
+		assert_eq!(added, [true, true, false, false]);
+		assert_eq!(book.entries().len(), 4);
+		assert_eq!(weights(&book, ""), moves(&[("e2e4", 2)]));
+		assert_eq!(weights(&book, "e4"), moves(&[("c7c5", 2)]));
+		assert_eq!(weights(&book, "e4 e5"), moves(&[("g1f3", 2)]));
+		assert!(weights(&book, "e4 e5 Nf3").is_empty());
+		assert_eq!(weights(&book, "e4 c5 Nf3"), moves(&[("d7d6", 2)]));
+	}
+	
+	#[test]
+	fn merge_and_scale() {
+		let games = games();
+		let twice = [games[0].clone(), games[0].clone()];
+		
+		let (book, _) = build(BookOptions::default(), &twice);
+		assert_eq!(book.entries().len(), 4);
+		assert_eq!(weights(&book, ""), moves(&[("e2e4", 4)]));
+		
+		let heavy = BookOptions {
+			win_points: u16::MAX,
+			..BookOptions::default()
+		};
+		let (book, _) = build(heavy, &[games[0].clone(), games[0].clone(), games[2].clone()]);
+		assert_eq!(weights(&book, ""), moves(&[("e2e4", u16::MAX), ("d2d4", 1)]));
+		assert_eq!(weights(&book, "e4"), moves(&[("e7e5", 0)]));
+	}
+}
@@ -0,0 +1,298 @@
+use std::hash::{Hash, Hasher};
+
+use crate::bitboard::{self, Bitboard};
+use crate::crazyhouse::Holdings;
+use crate::parser::{CastlingRights, Fen, Piece, PieceColor, PieceType, Row};
+use crate::square::{File, Rank, Square};
+use crate::variant::Variant;
+use crate::zobrist;
+
+/// A chess position stored as sets of squares, for fast questions about the whole board
+/// like where all the White pawns are or which squares Black attacks.
+///
+/// Converts to and from [`Fen`] without losing anything
+#[derive(Debug, Copy, Clone)]
+pub struct Position {
+	/// The squares of every piece type, in the order of [`PIECE_TYPES`]
+	by_type: [Bitboard; 6],
+	/// The squares of every White piece at index 0, and every Black piece at index 1
+	by_color: [Bitboard; 2],
+	pub side_to_move: PieceColor,
+	pub castling: CastlingRights,
+	pub en_passant: Option<Square>,
+	pub halfmove_clock: u32,
+	pub fullmove_number: u32,
+	/// The pieces in hand for crazyhouse and bughouse, `None` for variants without drops
+	pub holdings: Option<Holdings>,
+	/// The squares of pieces that were promoted from pawns
+	pub promoted: Bitboard,
+	/// The rules the game is played by
+	pub variant: Variant,
+	/// The number of checks White gave at index 0, and Black at index 1, which only Three-check counts
+	pub checks_given: [u8; 2],
+	/// The [`Position::zobrist_hash`], updated by [`Position::set_piece`] and [`Position::make_move`] as the position changes
+	pub(crate) hash: u64
+}
+
+/// Every piece type that takes up a square, in the order [`Position`] stores them
+pub const PIECE_TYPES: [PieceType; 6] = [
+	PieceType::Pawn,
+	PieceType::Rook,
+	PieceType::Knight,
+	PieceType::Bishop,
+	PieceType::Queen,
+	PieceType::King
+];
+
+impl Position {
+	/// A position of standard chess with no pieces, White to move and no castling
+	pub fn empty() -> Self {
+		let mut position = Position {
+			by_type: [Bitboard::EMPTY; 6],
+			by_color: [Bitboard::EMPTY; 2],
+			side_to_move: PieceColor::White,
+			castling: CastlingRights::none(),
+			en_passant: None,
+			halfmove_clock: 0,
+			fullmove_number: 1,
+			holdings: None,
+			promoted: Bitboard::EMPTY,
+			variant: Variant::Standard,
+			checks_given: [0; 2],
+			hash: 0
+		};
+		
+		position.refresh_zobrist_hash();
+		position
+	}
+	
+	/// Every square with a piece of the given type, of either color
+	pub fn by_type(&self, piece_type: PieceType) -> Bitboard {
+		match type_index(piece_type) {
+			Some(index) => self.by_type[index],
+			None => Bitboard::EMPTY
+		}
+	}
+	
+	/// Every square with a piece of the given color
+	pub fn by_color(&self, color: PieceColor) -> Bitboard {
+		match color_index(color) {
+			Some(index) => self.by_color[index],
+			None => Bitboard::EMPTY
+		}
+	}
+	
+	/// Every square with a piece of the given type and color
+	pub fn pieces(&self, piece_type: PieceType, color: PieceColor) -> Bitboard {
+		self.by_type(piece_type) & self.by_color(color)
+	}
+	
+	/// Every square with a piece on it
+	pub fn occupied(&self) -> Bitboard {
+		self.by_color[0] | self.by_color[1]
+	}
+	
+	/// The piece on the given square, which is [`Piece::air`] for an empty square
+	pub fn piece_at(&self, square: Square) -> Piece {
+		let color = if self.by_color[0].contains(square) {
+			PieceColor::White
+		} else if self.by_color[1].contains(square) {
+			PieceColor::Black
+		} else {
+			return Piece::air();
+		};
+		
+		let piece_type = PIECE_TYPES
+			.into_iter()
+			.zip(self.by_type)
+			.find(|(_, squares)| squares.contains(square))
+			.map_or(PieceType::Empty, |(piece_type, _)| piece_type);
+		
+		Piece {
+			piece_type,
+			color
+		}
+	}
+	
+	/// Puts a piece on the given square, replacing whatever was there,
+	/// and [`Piece::air`] empties the square
+	pub fn set_piece(&mut self, square: Square, piece: Piece) {
+		self.hash ^= zobrist::piece_key(self.piece_at(square), square) ^ zobrist::piece_key(piece, square);
+		
+		for squares in self.by_type.iter_mut().chain(self.by_color.iter_mut()) {
+			squares.remove(square);
+		}
+		
+		if let (Some(type_index), Some(color_index)) = (type_index(piece.piece_type), color_index(piece.color)) {
+			self.by_type[type_index].insert(square);
+			self.by_color[color_index].insert(square);
+		}
+	}
+	
+	/// The square of the king of the given color, or the lowest one if there are several
+	pub fn king(&self, color: PieceColor) -> Option<Square> {
+		self.pieces(PieceType::King, color).first()
+	}
+	
+	/// The squares the piece on the given square attacks, empty for an empty square
+	pub fn attacks_from(&self, square: Square) -> Bitboard {
+		let piece = self.piece_at(square);
+		let occupied = self.occupied();
+		
+		match piece.piece_type {
+			PieceType::Pawn => bitboard::pawn_attacks(piece.color, square),
+			PieceType::Rook => bitboard::rook_attacks(square, occupied),
+			PieceType::Knight => bitboard::knight_attacks(square),
+			PieceType::Bishop => bitboard::bishop_attacks(square, occupied),
+			PieceType::Queen => bitboard::queen_attacks(square, occupied),
+			PieceType::King => bitboard::king_attacks(square),
+			PieceType::Empty => Bitboard::EMPTY
+		}
+	}
+	
+	/// The squares of every piece of the given color that attacks the given square,
+	/// as if the board was occupied by `occupied`
+	pub fn attackers_with(&self, square: Square, color: PieceColor, occupied: Bitboard) -> Bitboard {
+		let rooks = self.by_type(PieceType::Rook) | self.by_type(PieceType::Queen);
+		let bishops = self.by_type(PieceType::Bishop) | self.by_type(PieceType::Queen);
+		
+		let attackers = bitboard::pawn_attacks(color.opposite(), square) & self.by_type(PieceType::Pawn)
+			| bitboard::knight_attacks(square) & self.by_type(PieceType::Knight)
+			| bitboard::king_attacks(square) & self.by_type(PieceType::King)
+			| bitboard::rook_attacks(square, occupied) & rooks
+			| bitboard::bishop_attacks(square, occupied) & bishops;
+		
+		attackers & self.by_color(color) & occupied
+	}
+	
+	/// The squares of every piece of the given color that attacks the given square
+	pub fn attackers(&self, square: Square, color: PieceColor) -> Bitboard {
+		self.attackers_with(square, color, self.occupied())
+	}
+	
+	/// Every square attacked by at least one piece of the given color
+	pub fn attacked_squares(&self, color: PieceColor) -> Bitboard {
+		self.by_color(color)
+			.into_iter()
+			.fold(Bitboard::EMPTY, |attacked, square| attacked | self.attacks_from(square))
+	}
+}
+
+/// The index of a piece type in [`PIECE_TYPES`], `None` for [`PieceType::Empty`]
+pub(crate) fn type_index(piece_type: PieceType) -> Option<usize> {
+	PIECE_TYPES.iter().position(|&other| other == piece_type)
+}
+
+/// The index of a color in [`Position`], `None` for [`PieceColor::Empty`]
+pub(crate) fn color_index(color: PieceColor) -> Option<usize> {
+	match color {
+		PieceColor::White => Some(0),
+		PieceColor::Black => Some(1),
+		PieceColor::Empty => None
+	}
+}
+
+/// Two positions are equal when everything but the cached hash is, so a hash
+/// that went stale after changing a public field does not make equal positions differ
+impl PartialEq for Position {
+	fn eq(&self, other: &Self) -> bool {
+		self.fields() == other.fields()
+	}
+}
+
+impl Eq for Position {}
+
+/// Hashes what [`PartialEq`] compares, leaving out the cached hash
+impl Hash for Position {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.fields().hash(state);
+	}
+}
+
+impl Position {
+	/// Every field but the cached hash, which is what equality and hashing look at
+	#[allow(clippy::type_complexity)]
+	fn fields(&self) -> (&[Bitboard; 6], &[Bitboard; 2], PieceColor, CastlingRights, Option<Square>, u32, u32, &Option<Holdings>, Bitboard, Variant, [u8; 2]) {
+		(
+			&self.by_type,
+			&self.by_color,
+			self.side_to_move,
+			self.castling,
+			self.en_passant,
+			self.halfmove_clock,
+			self.fullmove_number,
+			&self.holdings,
+			self.promoted,
+			self.variant,
+			self.checks_given
+		)
+	}
+}
+
+impl Default for Position {
+	/// The starting position for a chess game
+	fn default() -> Self {
+		Position::from(Fen::default())
+	}
+}
+
+impl From<Fen> for Position {
+	fn from(fen: Fen) -> Self {
+		let mut position = Position {
+			side_to_move: fen.side_to_move,
+			castling: fen.castling,
+			en_passant: fen.en_passant,
+			halfmove_clock: fen.halfmove_clock,
+			fullmove_number: fen.fullmove_number,
+			holdings: fen.holdings,
+			promoted: fen.promoted,
+			variant: fen.variant,
+			checks_given: fen.checks_given,
+			..Position::empty()
+		};
+		
+		for square in Square::all() {
+			position.set_piece(square, fen[square]);
+		}
+		
+		position.refresh_zobrist_hash();
+		position
+	}
+}
+
+impl From<&Fen> for Position {
+	fn from(fen: &Fen) -> Self {
+		Position::from(*fen)
+	}
+}
+
+impl From<Position> for Fen {
+	fn from(position: Position) -> Self {
+		let mut rows = [Row::empty(); 8];
+		
+		for rank in Rank::ALL {
+			for file in File::ALL {
+				rows[rank.index()].pieces[file.index()] = position.piece_at(Square::new(file, rank));
+			}
+		}
+		
+		Fen {
+			rows,
+			side_to_move: position.side_to_move,
+			castling: position.castling,
+			en_passant: position.en_passant,
+			halfmove_clock: position.halfmove_clock,
+			fullmove_number: position.fullmove_number,
+			holdings: position.holdings,
+			promoted: position.promoted,
+			variant: position.variant,
+			checks_given: position.checks_given
+		}
+	}
+}
+
+impl From<&Position> for Fen {
+	fn from(position: &Position) -> Self {
+		Fen::from(*position)
+	}
+}
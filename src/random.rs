@@ -0,0 +1,39 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// A small, fast random number generator (SplitMix64), which always gives the same numbers for the same seed.
+/// 
+/// It is meant for picking moves and positions, not for anything that needs to be unpredictable
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Random {
+	state: u64
+}
+
+impl Random {
+	/// A generator that gives the same numbers every time it is made with the same seed
+	pub fn new(seed: u64) -> Self {
+		Random {
+			state: seed
+		}
+	}
+	
+	/// A generator seeded differently every time, from the random keys the standard library uses for hash maps
+	pub fn from_entropy() -> Self {
+		Random::new(RandomState::new().build_hasher().finish())
+	}
+	
+	pub fn next_u64(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		
+		let mut z = self.state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		z ^ (z >> 31)
+	}
+	
+	/// A number from 0 up to but not including `bound`, which must not be 0
+	pub fn below(&mut self, bound: u64) -> u64 {
+		// Multiplying instead of taking the remainder keeps the numbers close to evenly spread
+		((u128::from(self.next_u64()) * u128::from(bound)) >> 64) as u64
+	}
+}
@@ -0,0 +1,325 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::moves::{CastlingSide, Move, MoveKind};
+use crate::parser::{Fen, Piece, PieceColor, PieceType};
+use crate::position::Position;
+use crate::square::{File, Rank, Square};
+use crate::status::GameStatus;
+
+/// The reason a move in Standard Algebraic Notation could not be read in a position
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SanError {
+	/// The text is not written like a SAN move
+	Syntax,
+	/// No legal move in the position matches the text
+	NoMatchingMove,
+	/// More than one legal move in the position matches the text
+	Ambiguous
+}
+
+impl Position {
+	/// Finds the legal move described by a move in Standard Algebraic Notation like `Nbd7`, `exd8=Q+` or `O-O-O`.
+	///
+	/// Trailing check, mate and annotation symbols like `+`, `#`, `!` and `?` are ignored,
+	/// castling can be written with `O` or `0`, and the `=` before a promotion can be left out.
+	/// Crazyhouse drops are written like `N@f3`, where the `P` of a pawn drop can be left out
+	pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+		let san = san
+			.trim()
+			.trim_end_matches(" e.p.")
+			.trim_end_matches(['+', '#', '!', '?']);
+		
+		match san {
+			"O-O" | "0-0" => return self.find_castling(CastlingSide::KingSide),
+			"O-O-O" | "0-0-0" => return self.find_castling(CastlingSide::QueenSide),
+			_ => {}
+		}
+		
+		if let Some((piece, square)) = san.split_once('@') {
+			return self.find_drop(piece, square);
+		}
+		
+		let mut characters: Vec<char> = san.chars().collect();
+		
+		// A king only comes up as a promotion in Antichess
+		let promotion = match characters.last().copied().and_then(piece_type) {
+			Some(piece_type) => {
+				characters.pop();
+				if characters.last() == Some(&'=') {
+					characters.pop();
+				}
+				Some(piece_type)
+			}
+			None => None
+		};
+		
+		if characters.len() < 2 {
+			return Err(SanError::Syntax);
+		}
+		
+		let rank = Rank::from_char(characters.pop().unwrap_or_default()).ok_or(SanError::Syntax)?;
+		let file = File::from_char(characters.pop().unwrap_or_default()).ok_or(SanError::Syntax)?;
+		let to = Square::new(file, rank);
+		
+		let piece_type = match characters.first().copied().and_then(piece_type) {
+			Some(piece_type) => {
+				characters.remove(0);
+				piece_type
+			}
+			None => PieceType::Pawn
+		};
+		
+		if characters.last() == Some(&'x') {
+			characters.pop();
+		}
+		
+		let mut from_file = None;
+		let mut from_rank = None;
+		
+		for character in characters {
+			if let Some(file) = File::from_char(character).filter(|_| from_file.is_none() && from_rank.is_none()) {
+				from_file = Some(file);
+			} else if let Some(rank) = Rank::from_char(character).filter(|_| from_rank.is_none()) {
+				from_rank = Some(rank);
+			} else {
+				return Err(SanError::Syntax);
+			}
+		}
+		
+		let mut matches = self.legal_moves().into_iter().filter(|mv| {
+			let move_promotion = match mv.kind {
+				MoveKind::Promotion(piece_type) => Some(piece_type),
+				MoveKind::Castle(_) => return false,
+				_ => None
+			};
+			
+			mv.to == to
+				&& self.piece_at(mv.from).piece_type == piece_type
+				&& move_promotion == promotion
+				&& from_file.is_none_or(|file| mv.from.file() == file)
+				&& from_rank.is_none_or(|rank| mv.from.rank() == rank)
+		});
+		
+		match (matches.next(), matches.next()) {
+			(Some(mv), None) => Ok(mv),
+			(Some(_), Some(_)) => Err(SanError::Ambiguous),
+			(None, _) => Err(SanError::NoMatchingMove)
+		}
+	}
+	
+	/// Writes a legal move in Standard Algebraic Notation, with just enough of the starting square
+	/// to tell it apart from other moves, and `+` or `#` if it gives check or mate.
+	/// 
+	/// A move that wins by the rules of the variant, like a king reaching the center in King of the Hill, also gets a `#`
+	pub fn san(&self, mv: Move) -> String {
+		let mut san = String::new();
+		let piece = self.piece_at(mv.from);
+		let captures = mv.kind == MoveKind::EnPassant
+			|| (self.piece_at(mv.to).color == piece.color.opposite() && matches!(mv.kind, MoveKind::Normal | MoveKind::Promotion(_)));
+		
+		match mv.kind {
+			MoveKind::Castle(CastlingSide::KingSide) => san.push_str("O-O"),
+			MoveKind::Castle(CastlingSide::QueenSide) => san.push_str("O-O-O"),
+			MoveKind::Drop(_) => san.push_str(&mv.to_string()),
+			_ if piece.piece_type == PieceType::Pawn => {
+				if captures {
+					san.push(mv.from.file().to_char());
+					san.push('x');
+				}
+				san.push_str(&mv.to.to_string());
+				
+				if let MoveKind::Promotion(piece_type) = mv.kind {
+					san.push('=');
+					san.push_str(&Piece::new(piece_type, PieceColor::White).to_string());
+				}
+			}
+			_ => {
+				san.push_str(&Piece::new(piece.piece_type, PieceColor::White).to_string());
+				san.push_str(&self.disambiguation(mv, piece.piece_type));
+				if captures {
+					san.push('x');
+				}
+				san.push_str(&mv.to.to_string());
+			}
+		}
+		
+		let after = self.play(mv);
+		let variant_win = after.variant_status() == Some(GameStatus::VariantWin(self.side_to_move));
+		
+		if after.is_checkmate() || variant_win {
+			san.push('#');
+		} else if after.is_check() {
+			san.push('+');
+		}
+		
+		san
+	}
+	
+	/// The file, rank or whole square needed to tell the move apart from
+	/// other legal moves of the same piece type to the same square
+	fn disambiguation(&self, mv: Move, piece_type: PieceType) -> String {
+		let others: Vec<Square> = self
+			.legal_moves()
+			.into_iter()
+			.filter(|other| other.to == mv.to && other.from != mv.from && !matches!(other.kind, MoveKind::Castle(_)))
+			.filter(|other| self.piece_at(other.from).piece_type == piece_type)
+			.map(|other| other.from)
+			.collect();
+		
+		if others.is_empty() {
+			String::new()
+		} else if others.iter().all(|other| other.file() != mv.from.file()) {
+			mv.from.file().to_string()
+		} else if others.iter().all(|other| other.rank() != mv.from.rank()) {
+			mv.from.rank().to_string()
+		} else {
+			mv.from.to_string()
+		}
+	}
+	
+	fn find_castling(&self, side: CastlingSide) -> Result<Move, SanError> {
+		self.legal_moves()
+			.into_iter()
+			.find(|mv| mv.kind == MoveKind::Castle(side))
+			.ok_or(SanError::NoMatchingMove)
+	}
+	
+	/// Finds the legal drop of the piece with the given letter, or a pawn for no letter, onto the square
+	fn find_drop(&self, piece: &str, square: &str) -> Result<Move, SanError> {
+		let mut letters = piece.chars();
+		let piece_type = match (letters.next(), letters.next()) {
+			(None | Some('P'), None) => PieceType::Pawn,
+			(Some(letter), None) => promotion_type(letter).ok_or(SanError::Syntax)?,
+			_ => return Err(SanError::Syntax)
+		};
+		let to = Square::from_algebraic(square).ok_or(SanError::Syntax)?;
+		
+		self.legal_moves()
+			.into_iter()
+			.find(|&mv| mv == Move::new_drop(piece_type, to))
+			.ok_or(SanError::NoMatchingMove)
+	}
+}
+
+impl Fen {
+	/// Finds the legal move described by a move in Standard Algebraic Notation, see [`Position::parse_san`]
+	pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+		Position::from(self).parse_san(san)
+	}
+	
+	/// Writes a legal move in Standard Algebraic Notation, see [`Position::san`]
+	pub fn san(&self, mv: Move) -> String {
+		Position::from(self).san(mv)
+	}
+}
+
+/// The piece type of an uppercase SAN piece letter, not counting pawns
+fn piece_type(character: char) -> Option<PieceType> {
+	match character {
+		'K' => Some(PieceType::King),
+		_ => promotion_type(character)
+	}
+}
+
+/// The piece type of an uppercase SAN promotion letter
+fn promotion_type(character: char) -> Option<PieceType> {
+	match character {
+		'Q' => Some(PieceType::Queen),
+		'R' => Some(PieceType::Rook),
+		'B' => Some(PieceType::Bishop),
+		'N' => Some(PieceType::Knight),
+		_ => None
+	}
+}
+
+impl Display for SanError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			SanError::Syntax => write!(f, "not a move in Standard Algebraic Notation"),
+			SanError::NoMatchingMove => write!(f, "no legal move matches"),
+			SanError::Ambiguous => write!(f, "more than one legal move matches")
+		}
+	}
+}
+
+impl Error for SanError {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parser::ParseOptions;
+	use crate::variant::Variant;
+	
+	const POSITIONS: [&str; 4] = [
+		"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+		"rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+		"rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+		"r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1"
+	];
+	
+	#[test]
+	fn round_trip() {
+		for fen in POSITIONS {
+			let position = Position::from(Fen::parse(fen).unwrap());
+			
+			for mv in position.legal_moves() {
+				assert_eq!(position.parse_san(&position.san(mv)), Ok(mv), "{} in {fen}", position.san(mv));
+			}
+		}
+	}
+	
+	#[test]
+	fn special_moves() {
+		let kiwipete = Position::from(Fen::parse(POSITIONS[0]).unwrap());
+		let en_passant = Position::from(Fen::parse(POSITIONS[1]).unwrap());
+		let promotion = Position::from(Fen::parse(POSITIONS[2]).unwrap());
+		let e1 = Square::from_algebraic("e1").unwrap();
+		
+		let castle = kiwipete.parse_san("O-O").unwrap();
+		assert_eq!((castle.from, castle.kind), (e1, MoveKind::Castle(CastlingSide::KingSide)));
+		assert_eq!(kiwipete.san(kiwipete.parse_san("0-0-0").unwrap()), "O-O-O");
+		
+		let capture = en_passant.parse_san("exf6").unwrap();
+		assert_eq!(capture.kind, MoveKind::EnPassant);
+		assert_eq!(en_passant.san(capture), "exf6");
+		
+		let queen = promotion.parse_san("dxc8Q").unwrap();
+		assert_eq!(queen.kind, MoveKind::Promotion(PieceType::Queen));
+		assert_eq!(promotion.san(queen), "dxc8=Q");
+		assert_eq!(promotion.san(promotion.parse_san("dxc8=N").unwrap()), "dxc8=N");
+	}
+	
+	#[test]
+	fn check_and_mate() {
+		let position = Position::from(Fen::parse("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2").unwrap());
+		let mate = position.parse_san("Qh4").unwrap();
+		
+		assert_eq!(position.san(mate), "Qh4#");
+		assert_eq!(position.parse_san("Qh4#"), Ok(mate));
+		assert_eq!(position.parse_san("Qg5"), Ok(Move::new(Square::from_algebraic("d8").unwrap(), Square::from_algebraic("g5").unwrap())));
+		assert_eq!(position.parse_san("Nd4"), Err(SanError::NoMatchingMove));
+		assert_eq!(position.parse_san("Zz9"), Err(SanError::Syntax));
+	}
+	
+	#[test]
+	fn variant_wins() {
+		let win = |variant: Variant, fen: &str, san: &str| {
+			let options = ParseOptions {
+				variant,
+				..ParseOptions::default()
+			};
+			let position = Position::from(Fen::parse_with(fen, options).unwrap());
+			position.san(position.parse_san(san).unwrap())
+		};
+		
+		assert_eq!(win(Variant::KingOfTheHill, "4k3/8/8/8/8/3K4/8/8 w - - 0 1", "Kd4"), "Kd4#");
+		assert_eq!(win(Variant::KingOfTheHill, "4k3/8/8/8/8/3K4/8/8 w - - 0 1", "Kc4"), "Kc4");
+		assert_eq!(win(Variant::Atomic, "4k3/4r3/8/8/8/8/8/4RK2 w - - 0 1", "Rxe7"), "Rxe7#");
+		assert_eq!(win(Variant::RacingKings, "8/6K1/8/8/8/8/k7/8 w - - 0 1", "Kh8"), "Kh8#");
+		assert_eq!(win(Variant::RacingKings, "8/8/8/8/8/8/k5K1/8 w - - 0 1", "Kh3"), "Kh3");
+		assert_eq!(win(Variant::ThreeCheck, "4k3/8/8/8/8/8/8/R3K3 w - - 0 1 +2+0", "Ra8+"), "Ra8#");
+		assert_eq!(win(Variant::ThreeCheck, "4k3/8/8/8/8/8/8/R3K3 w - - 0 1 +1+0", "Ra8+"), "Ra8+");
+		assert_eq!(win(Variant::Antichess, "8/8/8/8/8/8/1p6/R7 w - - 0 1", "Rb1"), "Rb1");
+	}
+}
@@ -0,0 +1,293 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// A column of the chessboard, from file A on White's left to file H on White's right
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum File {
+	A,
+	B,
+	C,
+	D,
+	E,
+	F,
+	G,
+	H
+}
+
+/// A row of the chessboard, from rank 1 on White's side to rank 8 on Black's side
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Rank {
+	One,
+	Two,
+	Three,
+	Four,
+	Five,
+	Six,
+	Seven,
+	Eight
+}
+
+/// A square on the chessboard, stored as an index from 0 for A1 up to 63 for H8,
+/// going through the files of a rank before moving up to the next rank
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Square(u8);
+
+/// The error returned when a string is not a square, file or rank name
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ParseSquareError;
+
+impl File {
+	/// Every file, in order from file A to file H
+	pub const ALL: [File; 8] = [File::A, File::B, File::C, File::D, File::E, File::F, File::G, File::H];
+	
+	/// The file with the given index, where 0 is file A,
+	/// returning `None` for indices past file H
+	pub fn from_index(index: usize) -> Option<Self> {
+		File::ALL.get(index).copied()
+	}
+	
+	/// The index of the file, where 0 is file A
+	pub fn index(self) -> usize {
+		self as usize
+	}
+	
+	/// Converts a lowercase file letter like `e` to a [`File`]
+	pub fn from_char(character: char) -> Option<Self> {
+		match character {
+			'a'..='h' => File::from_index(character as usize - 'a' as usize),
+			_ => None
+		}
+	}
+	
+	/// The lowercase letter of the file
+	pub fn to_char(self) -> char {
+		char::from(b'a' + self as u8)
+	}
+	
+	/// The file `offset` files to the right, returning `None` if that is off the board
+	pub fn offset(self, offset: i32) -> Option<Self> {
+		let index = usize::try_from(self as i32 + offset).ok()?;
+		File::from_index(index)
+	}
+}
+
+impl Rank {
+	/// Every rank, in order from rank 1 to rank 8
+	pub const ALL: [Rank; 8] = [
+		Rank::One,
+		Rank::Two,
+		Rank::Three,
+		Rank::Four,
+		Rank::Five,
+		Rank::Six,
+		Rank::Seven,
+		Rank::Eight
+	];
+	
+	/// The rank with the given index, where 0 is rank 1,
+	/// returning `None` for indices past rank 8
+	pub fn from_index(index: usize) -> Option<Self> {
+		Rank::ALL.get(index).copied()
+	}
+	
+	/// The index of the rank, where 0 is rank 1
+	pub fn index(self) -> usize {
+		self as usize
+	}
+	
+	/// Converts a rank digit like `4` to a [`Rank`]
+	pub fn from_char(character: char) -> Option<Self> {
+		match character {
+			'1'..='8' => Rank::from_index(character as usize - '1' as usize),
+			_ => None
+		}
+	}
+	
+	/// The digit of the rank
+	pub fn to_char(self) -> char {
+		char::from(b'1' + self as u8)
+	}
+	
+	/// The rank `offset` ranks up towards rank 8, returning `None` if that is off the board
+	pub fn offset(self, offset: i32) -> Option<Self> {
+		let index = usize::try_from(self as i32 + offset).ok()?;
+		Rank::from_index(index)
+	}
+}
+
+impl Square {
+	pub fn new(file: File, rank: Rank) -> Self {
+		#[allow(clippy::cast_possible_truncation)]
+		Square((rank.index() * 8 + file.index()) as u8)
+	}
+	
+	/// The square with the given index, where 0 is A1, 7 is H1 and 63 is H8,
+	/// returning `None` for indices past H8
+	pub fn from_index(index: usize) -> Option<Self> {
+		#[allow(clippy::cast_possible_truncation)]
+		(index < 64).then_some(Square(index as u8))
+	}
+	
+	/// The index of the square, where 0 is A1, 7 is H1 and 63 is H8
+	pub fn index(self) -> usize {
+		usize::from(self.0)
+	}
+	
+	pub fn file(self) -> File {
+		File::ALL[self.index() % 8]
+	}
+	
+	pub fn rank(self) -> Rank {
+		Rank::ALL[self.index() / 8]
+	}
+	
+	/// Converts a square name like `e4` to a [`Square`],
+	/// returning `None` for anything that is not a square name
+	pub fn from_algebraic(name: &str) -> Option<Self> {
+		let mut characters = name.chars();
+		
+		let file = File::from_char(characters.next()?)?;
+		let rank = Rank::from_char(characters.next()?)?;
+		
+		if characters.next().is_some() {
+			return None;
+		}
+		
+		Some(Square::new(file, rank))
+	}
+	
+	/// The square `file_offset` files to the right and `rank_offset` ranks up,
+	/// returning `None` if that is off the board
+	pub fn offset(self, file_offset: i32, rank_offset: i32) -> Option<Self> {
+		Some(Square::new(self.file().offset(file_offset)?, self.rank().offset(rank_offset)?))
+	}
+	
+	/// Every square, in order from A1 to H8
+	pub fn all() -> impl Iterator<Item = Square> {
+		(0..64).map(Square)
+	}
+}
+
+impl FromStr for File {
+	type Err = ParseSquareError;
+	
+	fn from_str(input: &str) -> Result<Self, Self::Err> {
+		let mut characters = input.chars();
+		
+		match (characters.next().and_then(File::from_char), characters.next()) {
+			(Some(file), None) => Ok(file),
+			_ => Err(ParseSquareError)
+		}
+	}
+}
+
+impl FromStr for Rank {
+	type Err = ParseSquareError;
+	
+	fn from_str(input: &str) -> Result<Self, Self::Err> {
+		let mut characters = input.chars();
+		
+		match (characters.next().and_then(Rank::from_char), characters.next()) {
+			(Some(rank), None) => Ok(rank),
+			_ => Err(ParseSquareError)
+		}
+	}
+}
+
+impl FromStr for Square {
+	type Err = ParseSquareError;
+	
+	fn from_str(input: &str) -> Result<Self, Self::Err> {
+		Square::from_algebraic(input).ok_or(ParseSquareError)
+	}
+}
+
+impl TryFrom<usize> for Square {
+	type Error = ParseSquareError;
+	
+	fn try_from(index: usize) -> Result<Self, Self::Error> {
+		Square::from_index(index).ok_or(ParseSquareError)
+	}
+}
+
+impl From<Square> for usize {
+	fn from(square: Square) -> Self {
+		square.index()
+	}
+}
+
+impl Display for File {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.to_char())
+	}
+}
+
+impl Display for Rank {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.to_char())
+	}
+}
+
+impl Display for Square {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}{}", self.file(), self.rank())
+	}
+}
+
+impl Display for ParseSquareError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "not a square name like e4")
+	}
+}
+
+impl Error for ParseSquareError {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn from_algebraic() {
+		assert_eq!(Square::from_algebraic("a1"), Some(Square::new(File::A, Rank::One)));
+		assert_eq!(Square::from_algebraic("e4"), Some(Square::new(File::E, Rank::Four)));
+		assert_eq!(Square::from_algebraic("h8").map(Square::index), Some(63));
+		
+		for name in ["i1", "a9", "a0", "a", "a10", "", "E4", "4e"] {
+			assert_eq!(Square::from_algebraic(name), None, "{name}");
+			assert_eq!(name.parse::<Square>(), Err(ParseSquareError), "{name}");
+		}
+		
+		assert_eq!("c".parse(), Ok(File::C));
+		assert_eq!("7".parse(), Ok(Rank::Seven));
+		assert_eq!("cd".parse::<File>(), Err(ParseSquareError));
+		assert_eq!("9".parse::<Rank>(), Err(ParseSquareError));
+	}
+	
+	#[test]
+	fn offset() {
+		let a1 = Square::new(File::A, Rank::One);
+		let h8 = Square::new(File::H, Rank::Eight);
+		
+		assert_eq!(a1.offset(1, 2), Square::from_algebraic("b3"));
+		assert_eq!(a1.offset(-1, 0), None);
+		assert_eq!(a1.offset(0, -1), None);
+		assert_eq!(a1.offset(7, 7), Some(h8));
+		assert_eq!(h8.offset(1, 0), None);
+		assert_eq!(h8.offset(0, 1), None);
+		assert_eq!(h8.offset(-7, -7), Some(a1));
+		assert_eq!(File::H.offset(1), None);
+		assert_eq!(Rank::One.offset(-1), None);
+	}
+	
+	#[test]
+	fn display_round_trip() {
+		for square in Square::all() {
+			assert_eq!(square.to_string().parse(), Ok(square));
+			assert_eq!(Square::try_from(square.index()), Ok(square));
+		}
+		
+		assert_eq!(Square::try_from(64), Err(ParseSquareError));
+		assert_eq!(Square::all().count(), 64);
+	}
+}
@@ -0,0 +1,203 @@
+use std::fmt::{Display, Formatter};
+
+use crate::bitboard::{self, Bitboard};
+use crate::draw::DrawReason;
+use crate::parser::{Fen, PieceColor};
+use crate::position::Position;
+use crate::variant::Variant;
+
+/// Whether the game goes on in a position, or how it ended
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum GameStatus {
+	/// The side to move has a legal move and no rule ends the game
+	Ongoing,
+	/// The side to move is in check and has no legal move, so it lost
+	Checkmate,
+	/// The side to move is not in check but has no legal move, which draws the game
+	Stalemate,
+	/// A rule ended the game in a draw
+	Draw(DrawReason),
+	/// A rule of the variant won the game for the given color, like giving the third check in Three-check
+	VariantWin(PieceColor),
+	/// A rule of the variant ended the game in a draw, like both kings reaching the eighth rank in Racing Kings
+	VariantDraw
+}
+
+impl GameStatus {
+	/// Whether the game is over
+	pub fn is_over(self) -> bool {
+		self != GameStatus::Ongoing
+	}
+}
+
+impl Position {
+	/// The pieces giving check to the side to move, which is never any in Antichess,
+	/// or in Atomic while the kings stand next to each other
+	pub fn checkers(&self) -> Bitboard {
+		let us = self.side_to_move;
+		let Some(king) = self.king(us) else {
+			return Bitboard::EMPTY;
+		};
+		
+		let kings_touch = self.king(us.opposite()).is_some_and(|theirs| bitboard::king_attacks(king).contains(theirs));
+		
+		match self.variant {
+			Variant::Antichess => Bitboard::EMPTY,
+			Variant::Atomic if kings_touch => Bitboard::EMPTY,
+			_ => self.attackers(king, us.opposite())
+		}
+	}
+	
+	/// Whether the king of the side to move is attacked
+	pub fn is_check(&self) -> bool {
+		!self.checkers().is_empty()
+	}
+	
+	/// Whether the side to move is in check and has no legal move, while no rule of the variant ended the game
+	pub fn is_checkmate(&self) -> bool {
+		self.is_check() && self.legal_moves().is_empty() && !self.is_variant_end()
+	}
+	
+	/// Whether the side to move is not in check but has no legal move, while no rule of the variant ended the game
+	pub fn is_stalemate(&self) -> bool {
+		!self.is_check() && self.legal_moves().is_empty() && !self.is_variant_end()
+	}
+	
+	/// Whether the game goes on or how it ended, counting only the draws that need no claim
+	/// and no earlier positions, which are the seventy-five-move rule and insufficient material.
+	/// 
+	/// The rules of the variant come first, see [`Position::variant_status`]
+	pub fn status(&self) -> GameStatus {
+		if let Some(status) = self.variant_status() {
+			return status;
+		}
+		
+		if self.legal_moves().is_empty() {
+			return if self.is_check() {
+				GameStatus::Checkmate
+			} else {
+				GameStatus::Stalemate
+			};
+		}
+		
+		if self.is_insufficient_material() {
+			GameStatus::Draw(DrawReason::InsufficientMaterial)
+		} else if self.is_seventy_five_moves() {
+			GameStatus::Draw(DrawReason::SeventyFiveMoves)
+		} else {
+			GameStatus::Ongoing
+		}
+	}
+}
+
+impl Fen {
+	/// The pieces giving check to the side to move, see [`Position::checkers`]
+	pub fn checkers(&self) -> Bitboard {
+		Position::from(self).checkers()
+	}
+	
+	/// Whether the king of the side to move is attacked
+	pub fn is_check(&self) -> bool {
+		Position::from(self).is_check()
+	}
+	
+	/// Whether the side to move is in check and has no legal move
+	pub fn is_checkmate(&self) -> bool {
+		Position::from(self).is_checkmate()
+	}
+	
+	/// Whether the side to move is not in check but has no legal move
+	pub fn is_stalemate(&self) -> bool {
+		Position::from(self).is_stalemate()
+	}
+	
+	/// Whether the game goes on or how it ended, see [`Position::status`]
+	pub fn status(&self) -> GameStatus {
+		Position::from(self).status()
+	}
+}
+
+impl Display for GameStatus {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			GameStatus::Ongoing => write!(f, "ongoing"),
+			GameStatus::Checkmate => write!(f, "checkmate"),
+			GameStatus::Stalemate => write!(f, "stalemate"),
+			GameStatus::Draw(reason) => write!(f, "draw by {reason}"),
+			GameStatus::VariantWin(PieceColor::Black) => write!(f, "win for Black by the rules of the variant"),
+			GameStatus::VariantWin(_) => write!(f, "win for White by the rules of the variant"),
+			GameStatus::VariantDraw => write!(f, "draw by the rules of the variant")
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parser::ParseOptions;
+	use crate::square::Square;
+	
+	fn position(fen: &str) -> Position {
+		Position::from(Fen::parse(fen).unwrap())
+	}
+	
+	#[test]
+	fn checkmate() {
+		let fools_mate = position("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3");
+		
+		assert!(fools_mate.is_check());
+		assert!(fools_mate.is_checkmate());
+		assert!(!fools_mate.is_stalemate());
+		assert_eq!(fools_mate.status(), GameStatus::Checkmate);
+		assert!(fools_mate.status().is_over());
+	}
+	
+	#[test]
+	fn stalemate() {
+		let stalemate = position("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+		
+		assert!(!stalemate.is_check());
+		assert!(stalemate.is_stalemate());
+		assert!(!stalemate.is_checkmate());
+		assert_eq!(stalemate.status(), GameStatus::Stalemate);
+	}
+	
+	#[test]
+	fn double_check() {
+		let double_check = position("4k3/8/8/1B6/8/8/8/4RK2 b - - 0 1");
+		let checkers: Bitboard = ["b5", "e1"].into_iter().map(|name| Square::from_algebraic(name).unwrap()).collect();
+		
+		assert_eq!(double_check.checkers(), checkers);
+		assert!(double_check.checkers().more_than_one());
+		assert!(double_check.legal_moves().iter().all(|mv| mv.from == Square::from_algebraic("e8").unwrap()));
+	}
+	
+	#[test]
+	fn status() {
+		assert_eq!(position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").status(), GameStatus::Ongoing);
+		assert_eq!(position("4k3/8/8/8/8/8/8/4KB2 w - - 0 1").status(), GameStatus::Draw(DrawReason::InsufficientMaterial));
+		assert_eq!(position("4k3/8/8/8/8/8/8/R3K3 w - - 150 100").status(), GameStatus::Draw(DrawReason::SeventyFiveMoves));
+		// Checkmate on the move that reaches the seventy-five-move limit still wins
+		assert_eq!(position("R3k3/8/4K3/8/8/8/8/8 b - - 150 100").status(), GameStatus::Checkmate);
+	}
+	
+	#[test]
+	fn variant_end() {
+		let options = |variant| ParseOptions {
+			variant,
+			..ParseOptions::default()
+		};
+		
+		// The mate is Black's third check, so the variant win comes first
+		let third_check = Position::from(Fen::parse_with("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3 +0+3", options(Variant::ThreeCheck)).unwrap());
+		assert!(third_check.is_check());
+		assert!(third_check.legal_moves().is_empty());
+		assert!(!third_check.is_checkmate());
+		assert_eq!(third_check.status(), GameStatus::VariantWin(PieceColor::Black));
+		
+		let exploded = Position::from(Fen::parse_with("rnb1kbnr/pppp1ppp/8/8/8/8/PPPPP1PP/RNBQ1BNR w kq - 0 3", options(Variant::Atomic)).unwrap());
+		assert!(!exploded.is_checkmate());
+		assert!(!exploded.is_stalemate());
+		assert_eq!(exploded.status(), GameStatus::VariantWin(PieceColor::Black));
+	}
+}
@@ -0,0 +1,193 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::moves::{Move, MoveKind};
+use crate::parser::{Fen, Piece, PieceColor, PieceType};
+use crate::position::Position;
+use crate::square::Square;
+
+/// How castling moves are written in UCI notation
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum CastlingMode {
+	/// The king moves two files, like `e1g1`
+	#[default]
+	Standard,
+	/// The king moves onto its own rook, like `e1h1`, which is how engines write castling in Chess960
+	Chess960
+}
+
+/// The reason a move in UCI notation could not be read in a position
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum UciError {
+	/// The text is not written like a UCI move such as `e2e4` or `e7e8q`
+	Syntax,
+	/// The move is not legal in the position
+	IllegalMove,
+	/// Both a king step and castling match the move, which happens in Chess960 with [`CastlingMode::Standard`]
+	/// when castling puts the king on a square next to it, so the castling move has to be written in [`CastlingMode::Chess960`]
+	Ambiguous
+}
+
+impl Position {
+	/// Finds the legal move described by a move in UCI long algebraic notation like `e2e4`,
+	/// `e7e8q` for a promotion, `N@f3` for a crazyhouse drop,
+	/// and `e1g1` or `e1h1` for castling depending on the [`CastlingMode`]
+	pub fn parse_uci(&self, uci: &str, mode: CastlingMode) -> Result<Move, UciError> {
+		let uci = uci.trim();
+		
+		if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+			return Err(UciError::Syntax);
+		}
+		
+		if let Some(square) = uci.strip_prefix(|character: char| character.is_ascii_alphabetic()).and_then(|rest| rest.strip_prefix('@')) {
+			let piece = Piece::from_char(uci.as_bytes()[0].to_ascii_uppercase().into()).ok_or(UciError::Syntax)?;
+			let to = Square::from_algebraic(square).ok_or(UciError::Syntax)?;
+			let mv = Move::new_drop(piece.piece_type, to);
+			
+			return if self.is_legal(mv) { Ok(mv) } else { Err(UciError::IllegalMove) };
+		}
+		
+		let from = Square::from_algebraic(&uci[0..2]).ok_or(UciError::Syntax)?;
+		let to = Square::from_algebraic(&uci[2..4]).ok_or(UciError::Syntax)?;
+		let promotion = match uci[4..].chars().next() {
+			Some('q') => Some(PieceType::Queen),
+			Some('r') => Some(PieceType::Rook),
+			Some('b') => Some(PieceType::Bishop),
+			Some('n') => Some(PieceType::Knight),
+			Some('k') => Some(PieceType::King),
+			Some(_) => return Err(UciError::Syntax),
+			None => None
+		};
+		
+		let moves = self.legal_moves();
+		let mut matches = moves.into_iter().filter(|&mv| {
+			let move_promotion = match mv.kind {
+				MoveKind::Promotion(piece_type) => Some(piece_type),
+				_ => None
+			};
+			
+			mv.from == from && self.uci_target(mv, mode) == to && move_promotion == promotion
+		});
+		
+		match (matches.next(), matches.next()) {
+			(Some(mv), None) => Ok(mv),
+			(Some(_), Some(_)) => Err(UciError::Ambiguous),
+			(None, _) => Err(UciError::IllegalMove)
+		}
+	}
+	
+	/// Writes a move in UCI long algebraic notation, with castling written according to the [`CastlingMode`]
+	pub fn uci(&self, mv: Move, mode: CastlingMode) -> String {
+		if let MoveKind::Drop(_) = mv.kind {
+			return mv.to_string();
+		}
+		
+		let mut uci = format!("{}{}", mv.from, self.uci_target(mv, mode));
+		
+		if let MoveKind::Promotion(piece_type) = mv.kind {
+			uci.push_str(&Piece::new(piece_type, PieceColor::Black).to_string());
+		}
+		
+		uci
+	}
+	
+	/// The target square of the move as UCI writes it, which is the rook for castling in Chess960
+	fn uci_target(&self, mv: Move, mode: CastlingMode) -> Square {
+		match (mv.kind, mode) {
+			(MoveKind::Castle(side), CastlingMode::Chess960) => {
+				self.castling_rook(self.side_to_move, side).unwrap_or(mv.to)
+			}
+			_ => mv.to
+		}
+	}
+}
+
+impl Fen {
+	/// Finds the legal move described by a move in UCI notation, see [`Position::parse_uci`]
+	pub fn parse_uci(&self, uci: &str, mode: CastlingMode) -> Result<Move, UciError> {
+		Position::from(self).parse_uci(uci, mode)
+	}
+	
+	/// Writes a move in UCI notation, see [`Position::uci`]
+	pub fn uci(&self, mv: Move, mode: CastlingMode) -> String {
+		Position::from(self).uci(mv, mode)
+	}
+}
+
+impl Display for UciError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			UciError::Syntax => write!(f, "not a move in UCI notation"),
+			UciError::IllegalMove => write!(f, "not a legal move in the position"),
+			UciError::Ambiguous => write!(f, "matches both a king move and castling in the position")
+		}
+	}
+}
+
+impl Error for UciError {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::moves::CastlingSide;
+	
+	const POSITIONS: [&str; 4] = [
+		"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+		"rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+		"rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+		"rkr5/pppppppp/8/8/8/8/PPPPPPPP/RKR5 w CAca - 0 1"
+	];
+	
+	#[test]
+	fn round_trip() {
+		for fen in POSITIONS {
+			let position = Position::from(Fen::parse(fen).unwrap());
+			
+			for mode in [CastlingMode::Standard, CastlingMode::Chess960] {
+				for mv in position.legal_moves() {
+					assert_eq!(position.parse_uci(&position.uci(mv, mode), mode), Ok(mv), "{} in {fen}", position.uci(mv, mode));
+				}
+			}
+		}
+	}
+	
+	#[test]
+	fn special_moves() {
+		let kiwipete = Position::from(Fen::parse(POSITIONS[0]).unwrap());
+		let en_passant = Position::from(Fen::parse(POSITIONS[1]).unwrap());
+		let promotion = Position::from(Fen::parse(POSITIONS[2]).unwrap());
+		let chess960 = Position::from(Fen::parse(POSITIONS[3]).unwrap());
+		
+		let castle = kiwipete.parse_uci("e1g1", CastlingMode::Standard).unwrap();
+		assert_eq!(castle.kind, MoveKind::Castle(CastlingSide::KingSide));
+		assert_eq!(kiwipete.uci(castle, CastlingMode::Chess960), "e1h1");
+		assert_eq!(kiwipete.parse_uci("e1a1", CastlingMode::Chess960).map(|mv| kiwipete.uci(mv, CastlingMode::Standard)), Ok("e1c1".to_string()));
+		
+		let castle = chess960.parse_uci("b1c1", CastlingMode::Chess960).unwrap();
+		assert_eq!(castle.kind, MoveKind::Castle(CastlingSide::KingSide));
+		assert_eq!(chess960.uci(castle, CastlingMode::Standard), "b1g1");
+		assert_eq!(chess960.parse_uci("b1g1", CastlingMode::Standard), Ok(castle));
+		assert_eq!(chess960.parse_uci("b1a1", CastlingMode::Chess960), Err(UciError::IllegalMove));
+		
+		let capture = en_passant.parse_uci("e5f6", CastlingMode::Standard).unwrap();
+		assert_eq!(capture.kind, MoveKind::EnPassant);
+		
+		let knight = promotion.parse_uci("d7c8n", CastlingMode::Standard).unwrap();
+		assert_eq!(knight.kind, MoveKind::Promotion(PieceType::Knight));
+		assert_eq!(promotion.uci(knight, CastlingMode::Standard), "d7c8n");
+		assert_eq!(promotion.parse_uci("d7c8", CastlingMode::Standard), Err(UciError::IllegalMove));
+		assert_eq!(promotion.parse_uci("d7c8x", CastlingMode::Standard), Err(UciError::Syntax));
+	}
+	
+	#[test]
+	fn ambiguous_castling() {
+		let position = Position::from(Fen::parse("4k2r/8/8/8/8/8/8/RK6 w Ah - 0 1").unwrap());
+		let castle = position.parse_uci("b1a1", CastlingMode::Chess960).unwrap();
+		let step = position.parse_uci("b1c1", CastlingMode::Chess960).unwrap();
+		
+		assert_eq!(castle.kind, MoveKind::Castle(CastlingSide::QueenSide));
+		assert_eq!(castle.to, Square::from_algebraic("c1").unwrap());
+		assert_eq!(step.kind, MoveKind::Normal);
+		assert_eq!(position.parse_uci("b1c1", CastlingMode::Standard), Err(UciError::Ambiguous));
+	}
+}
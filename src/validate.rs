@@ -0,0 +1,344 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::bitboard::Bitboard;
+use crate::moves::CastlingSide;
+use crate::parser::{Fen, Piece, PieceColor, PieceType};
+use crate::position::Position;
+use crate::square::{Rank, Square};
+use crate::variant::Variant;
+
+/// A reason the position could not come up in a game of chess
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ValidationError {
+	/// The color does not have exactly one king, or the number of kings its variant allows
+	KingCount { color: PieceColor, count: u32 },
+	/// A pawn stands on the first or last rank, where it can never be
+	PawnOnBackRank { square: Square },
+	/// The side that just moved left its own king in check
+	OpponentInCheck,
+	/// The color may castle to the side, but its king or rook is not where castling needs it
+	InvalidCastlingRights { color: PieceColor, side: CastlingSide },
+	/// The en passant square does not sit behind a pawn that could have just made a double step
+	InvalidEnPassant { square: Square },
+	/// The color has more pieces than its pawns could have promoted to,
+	/// counting pieces past the starting set like a second queen or a third knight as promoted
+	ImpossibleMaterial { color: PieceColor, pawns: u32, promoted: u32 },
+	/// The side to move is in check from more pieces than a single move can give check with
+	TooManyCheckers { count: u32 }
+}
+
+impl ValidationError {
+	/// A short name for the kind of problem, which stays the same between versions
+	/// and can be matched by programs instead of the message
+	pub fn code(&self) -> &'static str {
+		match self {
+			ValidationError::KingCount { .. } => "king_count",
+			ValidationError::PawnOnBackRank { .. } => "pawn_on_back_rank",
+			ValidationError::OpponentInCheck => "opponent_in_check",
+			ValidationError::InvalidCastlingRights { .. } => "invalid_castling_rights",
+			ValidationError::InvalidEnPassant { .. } => "invalid_en_passant",
+			ValidationError::ImpossibleMaterial { .. } => "impossible_material",
+			ValidationError::TooManyCheckers { .. } => "too_many_checkers"
+		}
+	}
+}
+
+impl Position {
+	/// Every reason the position could not come up in a game, which is empty for a valid position.
+	/// 
+	/// Parsing only checks that a FEN is well formed, so a board with three kings
+	/// or pawns on the first rank parses fine and only shows up as invalid here
+	pub fn validate(&self) -> Vec<ValidationError> {
+		let mut errors = Vec::new();
+		
+		// White has no king in Horde, the king is an ordinary piece in Antichess,
+		// and a king that blew up in Atomic ended the game
+		let allowed_kings = |color: PieceColor| match self.variant {
+			Variant::Antichess => 0..=u32::MAX,
+			Variant::Horde if color == PieceColor::White => 0..=0,
+			Variant::Atomic if self.king(color.opposite()).is_some() => 0..=1,
+			_ => 1..=1
+		};
+		
+		for color in [PieceColor::White, PieceColor::Black] {
+			let count = self.pieces(PieceType::King, color).count();
+			if !allowed_kings(color).contains(&count) {
+				errors.push(ValidationError::KingCount {
+					color,
+					count
+				});
+			}
+		}
+		
+		let mut back_ranks = Bitboard::rank(Rank::One) | Bitboard::rank(Rank::Eight);
+		if self.variant == Variant::Horde {
+			back_ranks -= Bitboard::rank(Rank::One) & self.by_color(PieceColor::White);
+		}
+		
+		for square in self.by_type(PieceType::Pawn) & back_ranks {
+			errors.push(ValidationError::PawnOnBackRank {
+				square
+			});
+		}
+		
+		let mut opponent_to_move = *self;
+		opponent_to_move.side_to_move = self.side_to_move.opposite();
+		// Blowing up the other king in Atomic wins even if it leaves the own king attacked
+		let king_blown_up = self.variant == Variant::Atomic && self.king(self.side_to_move).is_none();
+		if opponent_to_move.is_check() && !king_blown_up {
+			errors.push(ValidationError::OpponentInCheck);
+		}
+		
+		for color in [PieceColor::White, PieceColor::Black] {
+			for side in [CastlingSide::KingSide, CastlingSide::QueenSide] {
+				if self.castling.get(color, side) && !self.can_have_castling_right(color, side) {
+					errors.push(ValidationError::InvalidCastlingRights {
+						color,
+						side
+					});
+				}
+			}
+		}
+		
+		if let Some(square) = self.en_passant {
+			if !self.can_have_en_passant(square) {
+				errors.push(ValidationError::InvalidEnPassant {
+					square
+				});
+			}
+		}
+		
+		// Captured pieces change sides in crazyhouse, so any material can come back onto the board,
+		// and White starts with 36 pawns in Horde
+		let counts_material = |color: PieceColor| self.holdings.is_none() && !(self.variant == Variant::Horde && color == PieceColor::White);
+		
+		for color in [PieceColor::White, PieceColor::Black].into_iter().filter(|&color| counts_material(color)) {
+			let pawns = self.pieces(PieceType::Pawn, color).count();
+			let promoted = self.promoted_count(color);
+			
+			if pawns + promoted > 8 {
+				errors.push(ValidationError::ImpossibleMaterial {
+					color,
+					pawns,
+					promoted
+				});
+			}
+		}
+		
+		let count = self.checkers().count();
+		if count > 2 {
+			errors.push(ValidationError::TooManyCheckers {
+				count
+			});
+		}
+		
+		errors
+	}
+	
+	/// Whether the position could come up in a game, see [`Position::validate`]
+	pub fn is_valid(&self) -> bool {
+		self.validate().is_empty()
+	}
+	
+	/// Whether the king and rook stand where the castling right needs them:
+	/// the king on its first rank and the castling rook on the same rank on the right side of it
+	fn can_have_castling_right(&self, color: PieceColor, side: CastlingSide) -> bool {
+		let (Some(king), Some(rook)) = (self.king(color), self.castling.rook_square(color, side)) else {
+			return false;
+		};
+		
+		let home = match color {
+			PieceColor::White => Rank::One,
+			_ => Rank::Eight
+		};
+		let rook_on_side = match side {
+			CastlingSide::KingSide => rook.file() > king.file(),
+			CastlingSide::QueenSide => rook.file() < king.file()
+		};
+		
+		king.rank() == home && rook.rank() == home && rook_on_side && self.piece_at(rook) == Piece::new(PieceType::Rook, color)
+	}
+	
+	/// Whether the square is empty, on the third rank from the side that just moved,
+	/// with that side's pawn right in front of it and nothing on the square the pawn came from
+	fn can_have_en_passant(&self, square: Square) -> bool {
+		let them = self.side_to_move.opposite();
+		let (rank, forward) = match them {
+			PieceColor::White => (Rank::Three, 1),
+			PieceColor::Black => (Rank::Six, -1),
+			PieceColor::Empty => return false
+		};
+		
+		let pawn = square.offset(0, forward);
+		let origin = square.offset(0, -forward);
+		
+		square.rank() == rank
+			&& self.piece_at(square).piece_type == PieceType::Empty
+			&& pawn.is_some_and(|pawn| self.piece_at(pawn) == Piece::new(PieceType::Pawn, them))
+			&& origin.is_some_and(|origin| self.piece_at(origin).piece_type == PieceType::Empty)
+	}
+	
+	/// How many of the color's pieces must have come from promotions,
+	/// counting bishops separately for each square color since a bishop never changes color
+	fn promoted_count(&self, color: PieceColor) -> u32 {
+		let extra = |count: u32, start: u32| count.saturating_sub(start);
+		let bishops = self.pieces(PieceType::Bishop, color);
+		
+		extra(self.pieces(PieceType::Queen, color).count(), 1)
+			+ extra(self.pieces(PieceType::Rook, color).count(), 2)
+			+ extra(self.pieces(PieceType::Knight, color).count(), 2)
+			+ extra((bishops & Bitboard::DARK_SQUARES).count(), 1)
+			+ extra((bishops & Bitboard::LIGHT_SQUARES).count(), 1)
+	}
+}
+
+impl Fen {
+	/// Every reason the position could not come up in a game, see [`Position::validate`]
+	pub fn validate(&self) -> Vec<ValidationError> {
+		Position::from(self).validate()
+	}
+	
+	/// Whether the position could come up in a game, see [`Position::validate`]
+	pub fn is_valid(&self) -> bool {
+		Position::from(self).is_valid()
+	}
+}
+
+fn color_name(color: PieceColor) -> &'static str {
+	match color {
+		PieceColor::White => "White",
+		PieceColor::Black => "Black",
+		PieceColor::Empty => "Nobody"
+	}
+}
+
+impl Display for ValidationError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match *self {
+			ValidationError::KingCount { color, count } => {
+				let kings = if count == 1 { "king" } else { "kings" };
+				write!(f, "{} has {count} {kings}, which the rules of the variant do not allow", color_name(color))
+			}
+			ValidationError::PawnOnBackRank { square } => {
+				write!(f, "pawn on {square}, which is on a back rank")
+			}
+			ValidationError::OpponentInCheck => {
+				write!(f, "the side that just moved is in check")
+			}
+			ValidationError::InvalidCastlingRights { color, side } => {
+				let side = match side {
+					CastlingSide::KingSide => "king side",
+					CastlingSide::QueenSide => "queen side"
+				};
+				write!(f, "{} may castle on the {side}, but its king or rook has moved", color_name(color))
+			}
+			ValidationError::InvalidEnPassant { square } => {
+				write!(f, "en passant square {square} is not behind a pawn that just made a double step")
+			}
+			ValidationError::ImpossibleMaterial { color, pawns, promoted } => {
+				write!(f, "{} has {pawns} pawns and {promoted} promoted pieces, more than its 8 pawns allow", color_name(color))
+			}
+			ValidationError::TooManyCheckers { count } => {
+				write!(f, "the side to move is in check from {count} pieces")
+			}
+		}
+	}
+}
+
+impl Error for ValidationError {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parser::ParseOptions;
+	
+	fn validate(fen: &str) -> Vec<ValidationError> {
+		Fen::parse(fen).unwrap().validate()
+	}
+	
+	fn square(name: &str) -> Square {
+		Square::from_algebraic(name).unwrap()
+	}
+	
+	#[test]
+	fn valid_positions() {
+		assert!(Fen::default().is_valid());
+		assert!(validate("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").is_empty());
+	}
+	
+	#[test]
+	fn one_error_per_code() {
+		let cases = [
+			("8/8/8/8/8/8/8/4K3 w - - 0 1", ValidationError::KingCount { color: PieceColor::Black, count: 0 }),
+			("P3k3/8/8/8/8/8/8/4K3 w - - 0 1", ValidationError::PawnOnBackRank { square: square("a8") }),
+			("4k3/8/8/8/8/8/8/4RK2 w - - 0 1", ValidationError::OpponentInCheck),
+			("4k3/8/8/8/8/8/8/4K3 w K - 0 1", ValidationError::InvalidCastlingRights { color: PieceColor::White, side: CastlingSide::KingSide }),
+			("4k3/8/8/8/8/8/8/4K3 w - e6 0 1", ValidationError::InvalidEnPassant { square: square("e6") }),
+			("4k3/8/8/8/8/8/PPPPPPPP/QQ2K3 w - - 0 1", ValidationError::ImpossibleMaterial { color: PieceColor::White, pawns: 8, promoted: 1 }),
+			("4k3/8/3N4/1B6/8/8/8/4R1K1 b - - 0 1", ValidationError::TooManyCheckers { count: 3 })
+		];
+		
+		for (fen, error) in cases {
+			assert_eq!(validate(fen), vec![error], "{fen}");
+		}
+		
+		let codes: Vec<_> = cases.iter().map(|(_, error)| error.code()).collect();
+		assert_eq!(codes, [
+			"king_count",
+			"pawn_on_back_rank",
+			"opponent_in_check",
+			"invalid_castling_rights",
+			"invalid_en_passant",
+			"impossible_material",
+			"too_many_checkers"
+		]);
+	}
+	
+	#[test]
+	fn bishops_count_by_square_color() {
+		assert!(validate("4k3/8/8/8/8/8/PPPPPPPP/2B1KB2 w - - 0 1").is_empty());
+		assert_eq!(validate("4k3/8/8/8/8/8/PPPPPPPP/B1B1K3 w - - 0 1"), vec![
+			ValidationError::ImpossibleMaterial { color: PieceColor::White, pawns: 8, promoted: 1 }
+		]);
+	}
+	
+	#[test]
+	fn variant_exceptions() {
+		let validate_as = |variant, fen| {
+			let options = ParseOptions {
+				variant,
+				..ParseOptions::default()
+			};
+			Fen::parse_with(fen, options).unwrap().validate()
+		};
+		
+		assert!(validate_as(Variant::Horde, "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1").is_empty());
+		assert_eq!(validate_as(Variant::Horde, "4k3/8/8/8/8/8/8/4K3 w - - 0 1"), vec![
+			ValidationError::KingCount { color: PieceColor::White, count: 1 }
+		]);
+		assert_eq!(validate_as(Variant::Horde, "4k3/8/8/8/8/8/8/PPPPPPPp w - - 0 1"), vec![
+			ValidationError::PawnOnBackRank { square: square("h1") }
+		]);
+		
+		assert!(validate_as(Variant::Antichess, "8/8/8/8/8/8/8/K6K w - - 0 1").is_empty());
+		assert!(validate_as(Variant::Antichess, "8/8/8/8/8/8/8/N7 b - - 0 1").is_empty());
+		
+		// White's king blew up, so Black's king may stand attacked by the rook
+		assert!(validate_as(Variant::Atomic, "4k3/8/8/8/8/8/8/4R3 w - - 0 1").is_empty());
+		assert_eq!(validate_as(Variant::Atomic, "8/8/8/8/8/8/8/4R3 w - - 0 1"), vec![
+			ValidationError::KingCount { color: PieceColor::White, count: 0 },
+			ValidationError::KingCount { color: PieceColor::Black, count: 0 }
+		]);
+	}
+	
+	#[test]
+	fn messages() {
+		let no_king = ValidationError::KingCount { color: PieceColor::Black, count: 0 };
+		let one_king = ValidationError::KingCount { color: PieceColor::White, count: 1 };
+		
+		assert_eq!(no_king.to_string(), "Black has 0 kings, which the rules of the variant do not allow");
+		assert_eq!(one_king.to_string(), "White has 1 king, which the rules of the variant do not allow");
+		assert_eq!(ValidationError::TooManyCheckers { count: 3 }.to_string(), "the side to move is in check from 3 pieces");
+	}
+}
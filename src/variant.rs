@@ -0,0 +1,348 @@
+use std::fmt::{Display, Formatter};
+
+use crate::bitboard::{self, Bitboard};
+use crate::moves::{Move, MoveKind};
+use crate::parser::{Fen, ParseOptions, Piece, PieceColor, PieceType};
+use crate::position::{self, Position};
+use crate::square::{Rank, Square};
+use crate::status::GameStatus;
+
+/// The rules a game is played by, which decide what moves are legal, how the game ends and what the FEN holds.
+///
+/// Every variant plays on the same board with the same pieces, and [`Variant::Standard`] is plain chess
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Variant {
+	/// Standard chess, which also covers Chess960 since that only changes the starting position
+	#[default]
+	Standard,
+	/// Captured pieces go to the capturer's hand and can be dropped back onto the board as their own
+	Crazyhouse,
+	/// Giving check for the third time wins, with the checks given counted in the FEN like `+2+1`
+	ThreeCheck,
+	/// Bringing the king to one of the four center squares wins
+	KingOfTheHill,
+	/// Every capture explodes, removing the capturing piece and every piece but pawns next to the target square,
+	/// and blowing up the other king wins
+	Atomic,
+	/// Captures are forced, the king is an ordinary piece, and losing every piece or having no move wins
+	Antichess,
+	/// White has 36 pawns and no king and wins by checkmate, while Black wins by capturing every White piece
+	Horde,
+	/// Both sides start side by side on the first two ranks without pawns, no move may give check,
+	/// and the first king to reach the eighth rank wins
+	RacingKings
+}
+
+/// Every variant, starting with standard chess
+pub const VARIANTS: [Variant; 8] = [
+	Variant::Standard,
+	Variant::Crazyhouse,
+	Variant::ThreeCheck,
+	Variant::KingOfTheHill,
+	Variant::Atomic,
+	Variant::Antichess,
+	Variant::Horde,
+	Variant::RacingKings
+];
+
+/// The center squares a king has to reach in King of the Hill: d4, e4, d5 and e5
+pub const HILL: Bitboard = Bitboard(0x0000_0018_1800_0000);
+
+impl Variant {
+	/// The name of the variant as the PGN `Variant` tag writes it, like `King of the Hill`
+	pub fn name(self) -> &'static str {
+		match self {
+			Variant::Standard => "Standard",
+			Variant::Crazyhouse => "Crazyhouse",
+			Variant::ThreeCheck => "Three-check",
+			Variant::KingOfTheHill => "King of the Hill",
+			Variant::Atomic => "Atomic",
+			Variant::Antichess => "Antichess",
+			Variant::Horde => "Horde",
+			Variant::RacingKings => "Racing Kings"
+		}
+	}
+	
+	/// Reads a variant name the way PGN tags and chess servers write it, ignoring case, spaces, `-` and `_`,
+	/// so `King of the Hill`, `kingOfTheHill` and `koth` all work.
+	///
+	/// Chess960 and its other names read as [`Variant::Standard`], since only the starting position differs
+	pub fn from_name(name: &str) -> Option<Self> {
+		let name: String = name
+			.chars()
+			.filter(|character| !matches!(character, ' ' | '-' | '_'))
+			.map(|character| character.to_ascii_lowercase())
+			.collect();
+		
+		match name.as_str() {
+			"standard" | "chess" | "normal" | "fromposition" | "chess960" | "fischerandom" | "fischerrandom" => Some(Variant::Standard),
+			"crazyhouse" | "zh" => Some(Variant::Crazyhouse),
+			"threecheck" | "3check" => Some(Variant::ThreeCheck),
+			"kingofthehill" | "koth" => Some(Variant::KingOfTheHill),
+			"atomic" => Some(Variant::Atomic),
+			"antichess" | "giveaway" | "suicide" => Some(Variant::Antichess),
+			"horde" => Some(Variant::Horde),
+			"racingkings" => Some(Variant::RacingKings),
+			_ => None
+		}
+	}
+	
+	/// The position every game of the variant starts from
+	pub fn starting_fen(self) -> Fen {
+		let fen = match self {
+			Variant::Antichess => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+			Variant::Horde => "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1",
+			Variant::RacingKings => "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1",
+			_ => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+		};
+		let options = ParseOptions {
+			variant: self,
+			..ParseOptions::default()
+		};
+		
+		Fen::parse_with(fen, options).unwrap_or_else(|error| panic!("Invalid starting FEN for {}: {error}", self.name()))
+	}
+}
+
+impl Position {
+	/// How the game ended if a rule of the variant ended it, like a king reaching the center in King of the Hill
+	/// or a side running out of moves in Antichess, and `None` if no such rule applies
+	pub fn variant_status(&self) -> Option<GameStatus> {
+		let us = self.side_to_move;
+		let win = |color: PieceColor| Some(GameStatus::VariantWin(color));
+		
+		match self.variant {
+			Variant::Standard | Variant::Crazyhouse => None,
+			Variant::ThreeCheck => [PieceColor::White, PieceColor::Black]
+				.into_iter()
+				.find(|&color| self.checks_given(color) >= 3)
+				.and_then(win),
+			Variant::KingOfTheHill => [PieceColor::White, PieceColor::Black]
+				.into_iter()
+				.find(|&color| !(self.pieces(PieceType::King, color) & HILL).is_empty())
+				.and_then(win),
+			Variant::Atomic => match (self.king(PieceColor::White), self.king(PieceColor::Black)) {
+				(None, _) => win(PieceColor::Black),
+				(_, None) => win(PieceColor::White),
+				_ => None
+			},
+			Variant::Antichess if self.by_color(us.opposite()).is_empty() => win(us.opposite()),
+			// Every move is legal when the king is an ordinary piece, so having no pseudo-legal move means having no move
+			Variant::Antichess if self.pseudo_legal_moves().is_empty() => win(us),
+			Variant::Antichess => None,
+			Variant::Horde if self.by_color(PieceColor::White).is_empty() => win(PieceColor::Black),
+			Variant::Horde => None,
+			Variant::RacingKings => self.racing_kings_status()
+		}
+	}
+	
+	/// Whether a rule of the variant ended the game, see [`Position::variant_status`]
+	pub fn is_variant_end(&self) -> bool {
+		self.variant_status().is_some()
+	}
+	
+	/// The number of times the color has given check in Three-check
+	pub fn checks_given(&self, color: PieceColor) -> u8 {
+		position::color_index(color).map_or(0, |index| self.checks_given[index])
+	}
+	
+	/// Whether the move captures a piece, counting en passant
+	pub fn is_capture(&self, mv: Move) -> bool {
+		match mv.kind {
+			MoveKind::EnPassant => true,
+			MoveKind::Normal | MoveKind::Promotion(_) => self.by_color(self.side_to_move.opposite()).contains(mv.to),
+			MoveKind::Castle(_) | MoveKind::Drop(_) => false
+		}
+	}
+	
+	/// A draw when both kings reached the eighth rank, a win for the only king there,
+	/// except that Black gets one more move to catch up with a White king that got there first
+	fn racing_kings_status(&self) -> Option<GameStatus> {
+		let home = |color: PieceColor| self.king(color).is_some_and(|king| king.rank() == Rank::Eight);
+		
+		match (home(PieceColor::White), home(PieceColor::Black)) {
+			(true, true) => Some(GameStatus::VariantDraw),
+			(false, true) => Some(GameStatus::VariantWin(PieceColor::Black)),
+			(true, false) => {
+				let catches_up = self.side_to_move == PieceColor::Black
+					&& self.pseudo_legal_moves().into_iter().any(|mv| {
+						self.piece_at(mv.from).piece_type == PieceType::King && mv.to.rank() == Rank::Eight && self.leaves_king_safe(mv)
+					});
+				
+				(!catches_up).then_some(GameStatus::VariantWin(PieceColor::White))
+			}
+			(false, false) => None
+		}
+	}
+	
+	/// Whether the color can never win under the rules of the variant, `None` when the standard rules decide
+	pub(crate) fn variant_insufficient_material(&self, color: PieceColor) -> Option<bool> {
+		let kings = self.by_type(PieceType::King);
+		let ours = self.by_color(color);
+		let theirs = self.by_color(color.opposite());
+		
+		match self.variant {
+			Variant::Standard | Variant::Crazyhouse => None,
+			// Any piece but the king can give check
+			Variant::ThreeCheck => Some(ours == ours & kings),
+			// A king cannot capture, and a lone minor piece can neither mate nor reach a piece next to a bare king
+			Variant::Atomic => {
+				let minors = self.by_type(PieceType::Knight) | self.by_type(PieceType::Bishop);
+				let extra = ours - kings;
+				
+				Some(extra.is_empty() || (theirs == theirs & kings && extra.count() == 1 && !(extra & minors).is_empty()))
+			}
+			// Two lone bishops on squares of different colors can never capture each other
+			Variant::Antichess => {
+				let bishops = self.by_type(PieceType::Bishop);
+				let on_different_colors = !(bishops & Bitboard::DARK_SQUARES).is_empty() && !(bishops & Bitboard::LIGHT_SQUARES).is_empty();
+				
+				Some(ours.count() == 1 && theirs.count() == 1 && bishops == self.occupied() && on_different_colors)
+			}
+			Variant::KingOfTheHill | Variant::Horde | Variant::RacingKings => Some(false)
+		}
+	}
+	
+	/// The pieces an atomic capture destroys, the piece landing on the target square first
+	/// and then every piece but pawns on the squares next to it in square order,
+	/// with [`Piece::air`] for every square nothing is destroyed on
+	pub(crate) fn explosion(&self, mv: Move) -> [Piece; 9] {
+		let mut exploded = [Piece::air(); 9];
+		
+		if self.variant != Variant::Atomic || !self.is_capture(mv) {
+			return exploded;
+		}
+		
+		exploded[0] = match mv.kind {
+			MoveKind::Promotion(piece_type) => Piece::new(piece_type, self.side_to_move),
+			_ => self.piece_at(mv.from)
+		};
+		
+		for (slot, square) in exploded[1..].iter_mut().zip(bitboard::king_attacks(mv.to)) {
+			let piece = self.piece_at(square);
+			
+			if square != mv.from && piece.piece_type != PieceType::Pawn {
+				*slot = piece;
+			}
+		}
+		
+		exploded
+	}
+	
+	/// Empties the target square of an atomic capture and every square next to it that holds anything but a pawn
+	pub(crate) fn explode(&mut self, square: Square) {
+		self.set_piece(square, Piece::air());
+		
+		for neighbor in bitboard::king_attacks(square) - self.by_type(PieceType::Pawn) {
+			self.set_piece(neighbor, Piece::air());
+		}
+	}
+	
+	/// Puts back the pieces an atomic capture onto the square destroyed, see [`Position::explosion`]
+	pub(crate) fn restore_explosion(&mut self, square: Square, exploded: &[Piece; 9]) {
+		self.set_piece(square, exploded[0]);
+		
+		for (&piece, neighbor) in exploded[1..].iter().zip(bitboard::king_attacks(square)) {
+			if piece != Piece::air() {
+				self.set_piece(neighbor, piece);
+			}
+		}
+	}
+}
+
+impl Fen {
+	/// How the game ended if a rule of the variant ended it, see [`Position::variant_status`]
+	pub fn variant_status(&self) -> Option<GameStatus> {
+		Position::from(self).variant_status()
+	}
+}
+
+/// Writes the name of the variant as the PGN `Variant` tag writes it
+impl Display for Variant {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.name())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::uci::CastlingMode;
+	
+	fn position(variant: Variant, fen: &str) -> Position {
+		let options = ParseOptions {
+			variant,
+			..ParseOptions::default()
+		};
+		
+		Position::from(Fen::parse_with(fen, options).unwrap())
+	}
+	
+	#[test]
+	fn names() {
+		for variant in VARIANTS {
+			assert_eq!(Variant::from_name(variant.name()), Some(variant));
+			assert_eq!(variant.to_string(), variant.name());
+		}
+		
+		assert_eq!(Variant::from_name("kingOfTheHill"), Some(Variant::KingOfTheHill));
+		assert_eq!(Variant::from_name("KOTH"), Some(Variant::KingOfTheHill));
+		assert_eq!(Variant::from_name("racing_kings"), Some(Variant::RacingKings));
+		assert_eq!(Variant::from_name("Chess960"), Some(Variant::Standard));
+		assert_eq!(Variant::from_name("giveaway"), Some(Variant::Antichess));
+		assert_eq!(Variant::from_name("bughouse"), None);
+	}
+	
+	#[test]
+	fn starting_positions() {
+		for variant in VARIANTS {
+			let position = Position::from(variant.starting_fen());
+			
+			assert_eq!(position.variant, variant);
+			assert!(position.is_valid(), "{variant}");
+			assert_eq!(position.variant_status(), None, "{variant}");
+		}
+		
+		assert!(Variant::Crazyhouse.starting_fen().holdings.is_some());
+		assert!(Variant::Standard.starting_fen().holdings.is_none());
+	}
+	
+	#[test]
+	fn variant_wins() {
+		let cases = [
+			(Variant::ThreeCheck, "4k3/8/8/8/8/8/8/4K3 b - - 0 1 +3+0", Some(GameStatus::VariantWin(PieceColor::White))),
+			(Variant::ThreeCheck, "4k3/8/8/8/8/8/8/4K3 b - - 0 1 +2+2", None),
+			(Variant::KingOfTheHill, "8/8/8/4k3/8/8/8/4K3 w - - 0 1", Some(GameStatus::VariantWin(PieceColor::Black))),
+			(Variant::Atomic, "8/8/8/8/8/8/8/4K3 b - - 0 1", Some(GameStatus::VariantWin(PieceColor::White))),
+			(Variant::Antichess, "8/8/8/8/8/8/8/4K3 b - - 0 1", Some(GameStatus::VariantWin(PieceColor::Black))),
+			// White's blocked pawn leaves it without a move, which wins in Antichess
+			(Variant::Antichess, "8/8/8/8/8/p7/P7/8 w - - 0 1", Some(GameStatus::VariantWin(PieceColor::White))),
+			(Variant::Horde, "4k3/8/8/8/8/8/8/8 w - - 0 1", Some(GameStatus::VariantWin(PieceColor::Black))),
+			(Variant::RacingKings, "4K3/8/k7/8/8/8/8/8 b - - 0 1", Some(GameStatus::VariantWin(PieceColor::White))),
+			(Variant::RacingKings, "4K3/k7/8/8/8/8/8/8 b - - 0 1", None),
+			(Variant::RacingKings, "k3K3/8/8/8/8/8/8/8 w - - 0 1", Some(GameStatus::VariantDraw)),
+			(Variant::Standard, "4k3/8/8/8/8/8/8/4K3 w - - 0 1", None)
+		];
+		
+		for (variant, fen, status) in cases {
+			let position = position(variant, fen);
+			
+			assert_eq!(position.variant_status(), status, "{variant} {fen}");
+			assert_eq!(position.is_variant_end(), status.is_some(), "{variant} {fen}");
+		}
+	}
+	
+	#[test]
+	fn explosions() {
+		let mut position = position(Variant::Atomic, "4k3/8/8/3pnp2/4P3/3P4/8/4K3 w - - 0 1");
+		let capture = position.parse_uci("e4f5", CastlingMode::Standard).unwrap();
+		let before = position;
+		let undo = position.make_move(capture);
+		
+		// The pawns next to f5 survive, but the knight and both pieces on f5 are gone
+		assert_eq!(Fen::from(position).to_string(), "4k3/8/8/3p4/8/3P4/8/4K3 b - - 0 1");
+		
+		position.unmake_move(capture, undo);
+		assert_eq!(position, before);
+	}
+}